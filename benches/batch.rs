@@ -0,0 +1,86 @@
+use criterion::{Criterion, Throughput, black_box, criterion_group, criterion_main};
+use solana_hash::Hash;
+use solana_message::{MessageHeader, compiled_instruction::CompiledInstruction};
+use solana_pubkey::Pubkey;
+use v1tx::{batch::BatchParser, v3, versioned::VersionedMessage};
+
+const BATCH_SIZE: usize = 64;
+
+fn v3_message(seed: u8) -> v3::Message {
+    v3::Message {
+        compute_budget_header: v3::ComputeBudgetHeader::new(
+            Some(200_000),
+            Some(u64::from(seed) * 1_000),
+            None,
+            None,
+        ),
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 1,
+        },
+        account_keys: vec![
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ],
+        recent_blockhash: Hash::new_unique(),
+        instructions: vec![CompiledInstruction::new_from_raw_parts(
+            2,
+            vec![seed; 32],
+            vec![0, 1],
+        )],
+        address_table_lookups: vec![],
+    }
+}
+
+fn bench_batch(c: &mut Criterion) {
+    let messages: Vec<v3::Message> = (0..BATCH_SIZE as u8).map(v3_message).collect();
+    let raw: Vec<Vec<u8>> = messages
+        .iter()
+        .map(|message| bincode::serialize(message).unwrap())
+        .collect();
+    let versioned: Vec<Vec<u8>> = messages
+        .into_iter()
+        .map(|message| VersionedMessage::V3(message).serialize())
+        .collect();
+
+    let mut buffer = vec![];
+    let mut offsets = vec![0];
+    for packet in &versioned {
+        buffer.extend_from_slice(packet);
+        offsets.push(buffer.len());
+    }
+
+    let mut group = c.benchmark_group("batch");
+    group.throughput(Throughput::Elements(BATCH_SIZE as u64));
+
+    // the batch parser sanitizes every packet, so the baseline does too
+    group.bench_function("bincode_v3_sanitize_loop", |b| {
+        b.iter(|| {
+            for packet in &raw {
+                let message = bincode::deserialize::<v3::Message>(black_box(packet)).unwrap();
+                message.sanitize().unwrap();
+                black_box(message);
+            }
+        })
+    });
+
+    let mut parser = BatchParser::with_capacity(BATCH_SIZE);
+    group.bench_function("batch_parser", |b| {
+        b.iter(|| {
+            black_box(parser.parse(versioned.iter().map(Vec::as_slice)));
+        })
+    });
+
+    group.bench_function("batch_parser_contiguous", |b| {
+        b.iter(|| {
+            black_box(parser.parse_contiguous(black_box(&buffer), &offsets));
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_batch);
+criterion_main!(benches);
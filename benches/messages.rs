@@ -0,0 +1,296 @@
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_hash::Hash;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_message::{AddressLookupTableAccount, v0};
+use solana_pubkey::Pubkey;
+#[cfg(feature = "v3b")]
+use v1tx::v3b;
+use v1tx::{layout::MessageLayout, v1, v2, v3, v4, v5, versioned::VersionedMessage};
+
+struct Case {
+    name: &'static str,
+    compute_unit_limit: Option<u32>,
+    compute_unit_price: Option<u64>,
+    loaded_accounts_data_limit: Option<u32>,
+    requested_heap_bytes: Option<u32>,
+    instructions: Vec<Instruction>,
+    lookup_tables: Vec<AddressLookupTableAccount>,
+}
+
+impl Case {
+    fn budget_instructions(&self, skip_limit_price: bool) -> Vec<Instruction> {
+        let mut instructions = vec![];
+        if !skip_limit_price {
+            instructions.extend(
+                self.compute_unit_limit
+                    .map(ComputeBudgetInstruction::set_compute_unit_limit),
+            );
+            instructions.extend(
+                self.compute_unit_price
+                    .map(ComputeBudgetInstruction::set_compute_unit_price),
+            );
+        }
+        instructions.extend(
+            self.loaded_accounts_data_limit
+                .map(ComputeBudgetInstruction::set_loaded_accounts_data_size_limit),
+        );
+        instructions.extend(
+            self.requested_heap_bytes
+                .map(ComputeBudgetInstruction::request_heap_frame),
+        );
+        instructions
+    }
+
+    fn compile(&self, payer: &Pubkey, budget_instructions: Vec<Instruction>) -> v0::Message {
+        let mut instructions = budget_instructions;
+        instructions.extend(self.instructions.iter().cloned());
+        v0::Message::try_compile(
+            payer,
+            &instructions,
+            &self.lookup_tables,
+            Hash::new_unique(),
+        )
+        .unwrap()
+    }
+
+    /// The same request encoded as v0, v1, v2, v3, v4, v5 and v3b.
+    fn messages(&self) -> Vec<(&'static str, VersionedMessage)> {
+        let payer = Pubkey::new_unique();
+
+        let v0 = self.compile(&payer, self.budget_instructions(false));
+
+        let v1 = self.compile(&payer, self.budget_instructions(true));
+        let v1 = v1::Message {
+            header: v1::MessageHeader {
+                compute_unit_price: self.compute_unit_price.unwrap_or_default(),
+                compute_unit_limit: self.compute_unit_limit.unwrap_or_default(),
+                num_required_signatures: v1.header.num_required_signatures,
+                num_readonly_signed_accounts: v1.header.num_readonly_signed_accounts,
+                num_readonly_unsigned_accounts: v1.header.num_readonly_unsigned_accounts,
+            },
+            account_keys: v1.account_keys,
+            recent_blockhash: v1.recent_blockhash,
+            instructions: v1.instructions,
+            address_table_lookups: v1.address_table_lookups,
+        };
+
+        let v2 = self.compile(&payer, vec![]);
+        let v2 = v2::Message {
+            header: v2::MessageHeader {
+                compute_unit_price: self.compute_unit_price.unwrap_or_default(),
+                compute_unit_limit: self.compute_unit_limit.unwrap_or_default(),
+                loaded_accounts_data_limit: self.loaded_accounts_data_limit.unwrap_or_default(),
+                requested_heap_bytes: self.requested_heap_bytes.unwrap_or_default(),
+                num_required_signatures: v2.header.num_required_signatures,
+                num_readonly_signed_accounts: v2.header.num_readonly_signed_accounts,
+                num_readonly_unsigned_accounts: v2.header.num_readonly_unsigned_accounts,
+            },
+            account_keys: v2.account_keys,
+            recent_blockhash: v2.recent_blockhash,
+            instructions: v2.instructions,
+            address_table_lookups: v2.address_table_lookups,
+        };
+
+        let v3 = self.compile(&payer, vec![]);
+        let v3 = v3::Message {
+            compute_budget_header: v3::ComputeBudgetHeader::new(
+                self.compute_unit_limit,
+                self.compute_unit_price,
+                self.loaded_accounts_data_limit,
+                self.requested_heap_bytes,
+            ),
+            header: v3.header,
+            account_keys: v3.account_keys,
+            recent_blockhash: v3.recent_blockhash,
+            instructions: v3.instructions,
+            address_table_lookups: v3.address_table_lookups,
+        };
+
+        let v4 = self.compile(&payer, vec![]);
+        let v4 = v4::Message {
+            compute_budget_header: v4::ComputeBudgetHeader::new(
+                self.compute_unit_limit,
+                self.compute_unit_price,
+                self.loaded_accounts_data_limit,
+                self.requested_heap_bytes,
+            ),
+            header: v4.header,
+            account_keys: v4.account_keys,
+            recent_blockhash: v4.recent_blockhash,
+            instructions: v4.instructions,
+            address_table_lookups: v4.address_table_lookups,
+        };
+
+        let v5 = self.compile(&payer, vec![]);
+        let v5 = v5::Message {
+            compute_budget_header: v5::ComputeBudgetHeader::new(
+                self.compute_unit_limit,
+                self.compute_unit_price
+                    .map(|price| v5::ScaledPrice::try_from(price).unwrap()),
+                self.loaded_accounts_data_limit,
+                self.requested_heap_bytes,
+            ),
+            header: v5.header,
+            account_keys: v5.account_keys,
+            recent_blockhash: v5.recent_blockhash,
+            instructions: v5.instructions,
+            address_table_lookups: v5.address_table_lookups,
+        };
+
+        #[cfg(feature = "v3b")]
+        let v3b = v3b::Message::from(v3.clone());
+
+        #[allow(unused_mut)]
+        let mut messages = vec![
+            ("v0", VersionedMessage::V0(v0)),
+            ("v1", VersionedMessage::V1(v1)),
+            ("v2", VersionedMessage::V2(v2)),
+            ("v3", VersionedMessage::V3(v3)),
+            ("v4", VersionedMessage::V4(v4)),
+            ("v5", VersionedMessage::V5(v5)),
+        ];
+        #[cfg(feature = "v3b")]
+        messages.push(("v3b", VersionedMessage::V3b(v3b)));
+        messages
+    }
+}
+
+/// A message with many static accounts and two address table lookups.
+fn large_instructions() -> (Vec<Instruction>, Vec<AddressLookupTableAccount>) {
+    let program_id = Pubkey::new_unique();
+    let static_accounts: Vec<Pubkey> = (0..24).map(|_| Pubkey::new_unique()).collect();
+    let lookup_tables: Vec<AddressLookupTableAccount> = (0..2)
+        .map(|_| AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: (0..64).map(|_| Pubkey::new_unique()).collect(),
+        })
+        .collect();
+
+    let instructions = (0..8)
+        .map(|i| {
+            let mut accounts: Vec<AccountMeta> = static_accounts[i * 3..i * 3 + 3]
+                .iter()
+                .map(|key| AccountMeta::new(*key, false))
+                .collect();
+            for table in &lookup_tables {
+                accounts.extend(table.addresses[i * 8..i * 8 + 8].iter().enumerate().map(
+                    |(j, key)| match j % 2 {
+                        0 => AccountMeta::new(*key, false),
+                        _ => AccountMeta::new_readonly(*key, false),
+                    },
+                ));
+            }
+            Instruction::new_with_bytes(program_id, &[i as u8; 64], accounts)
+        })
+        .collect();
+
+    (instructions, lookup_tables)
+}
+
+fn cases() -> Vec<Case> {
+    let (large_instructions, large_lookup_tables) = large_instructions();
+    vec![
+        Case {
+            name: "noop",
+            compute_unit_limit: None,
+            compute_unit_price: None,
+            loaded_accounts_data_limit: None,
+            requested_heap_bytes: None,
+            instructions: vec![],
+            lookup_tables: vec![],
+        },
+        Case {
+            name: "limit_price",
+            compute_unit_limit: Some(12345),
+            compute_unit_price: Some(12345),
+            loaded_accounts_data_limit: None,
+            requested_heap_bytes: None,
+            instructions: vec![],
+            lookup_tables: vec![],
+        },
+        Case {
+            name: "full",
+            compute_unit_limit: Some(12345),
+            compute_unit_price: Some(12345),
+            loaded_accounts_data_limit: Some(12345),
+            requested_heap_bytes: Some(12345),
+            instructions: vec![],
+            lookup_tables: vec![],
+        },
+        Case {
+            name: "large",
+            compute_unit_limit: Some(1_400_000),
+            compute_unit_price: Some(12345),
+            loaded_accounts_data_limit: Some(12345),
+            requested_heap_bytes: Some(12345),
+            instructions: large_instructions,
+            lookup_tables: large_lookup_tables,
+        },
+    ]
+}
+
+fn bench_messages(c: &mut Criterion) {
+    let cases: Vec<(&str, Vec<(&str, VersionedMessage)>)> = cases()
+        .iter()
+        .map(|case| (case.name, case.messages()))
+        .collect();
+
+    let mut group = c.benchmark_group("encode");
+    for (case, messages) in &cases {
+        for (version, message) in messages {
+            group.bench_with_input(BenchmarkId::new(*version, case), message, |b, message| {
+                b.iter(|| black_box(message.serialize()))
+            });
+        }
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("decode");
+    for (case, messages) in &cases {
+        for (version, message) in messages {
+            let bytes = message.serialize();
+            group.bench_with_input(BenchmarkId::new(*version, case), &bytes, |b, bytes| {
+                b.iter(|| black_box(VersionedMessage::deserialize(black_box(bytes)).unwrap()))
+            });
+        }
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("sanitize");
+    for (case, messages) in &cases {
+        for (version, message) in messages {
+            group.bench_with_input(BenchmarkId::new(*version, case), message, |b, message| {
+                b.iter(|| black_box(message.sanitize()).unwrap())
+            });
+        }
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("compute_budget");
+    for (case, messages) in &cases {
+        for (version, message) in messages {
+            group.bench_with_input(BenchmarkId::new(*version, case), message, |b, message| {
+                b.iter(|| black_box(message.compute_budget_request().unwrap()))
+            });
+        }
+    }
+    group.finish();
+
+    // locating the budget fields in the raw bytes, which for v3b means walking
+    // the whole message first
+    let mut group = c.benchmark_group("field_ranges");
+    for (case, messages) in &cases {
+        for (version, message) in messages {
+            let layout = MessageLayout::of(message.version());
+            let bytes = message.serialize();
+            group.bench_with_input(BenchmarkId::new(*version, case), &bytes, |b, bytes| {
+                b.iter(|| black_box(layout.field_ranges(black_box(bytes)).unwrap()))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_messages);
+criterion_main!(benches);
@@ -1,7 +1,7 @@
 use solana_hash::Hash;
 use solana_message::{MessageHeader, v0};
 use solana_pubkey::Pubkey;
-use v1tx::{v1, v2, v3};
+use v1tx::{v1, v2, v3, v4, v5};
 
 fn main() {
     let blockhash = Hash::new_unique();
@@ -176,7 +176,7 @@ fn main() {
             num_readonly_signed_accounts: 0,
             num_readonly_unsigned_accounts: 0,
         },
-        account_keys: vec![payer], /* v3 will never need compute budget program. if solana ever gets to >8 limits we should press the off button and go home */
+        account_keys: vec![payer], /* v3 will never need compute budget program. past 7 limits the flags grow a continuation byte */
         recent_blockhash: blockhash,
         instructions: vec![],
         address_table_lookups: vec![],
@@ -193,7 +193,7 @@ fn main() {
             num_readonly_signed_accounts: 0,
             num_readonly_unsigned_accounts: 0,
         },
-        account_keys: vec![payer], /* v3 will never need compute budget program. if solana ever gets to >8 limits we should press the off button and go home */
+        account_keys: vec![payer], /* v3 will never need compute budget program. past 7 limits the flags grow a continuation byte */
         recent_blockhash: blockhash,
         instructions: vec![],
         address_table_lookups: vec![],
@@ -215,13 +215,150 @@ fn main() {
             num_readonly_signed_accounts: 0,
             num_readonly_unsigned_accounts: 0,
         },
-        account_keys: vec![payer], /* v3 will never need compute budget program. if solana ever gets to >8 limits we should press the off button and go home */
+        account_keys: vec![payer], /* v3 will never need compute budget program. past 7 limits the flags grow a continuation byte */
         recent_blockhash: blockhash,
         instructions: vec![],
         address_table_lookups: vec![],
     };
     println!(
-        "v3 with full cb ix set     = {}",
+        "v3 with full cb ix set     = {}\n",
         bincode::serialized_size(&v3_full).unwrap()
     );
+
+    // ————————————————
+    // v4: noop / limit+price / full
+    // ————————————————
+    // noop
+    let v4_noop = v4::Message {
+        compute_budget_header: v4::ComputeBudgetHeader::new(None, None, None, None),
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 0,
+        },
+        account_keys: vec![payer], /* same flags as v3, but 12345 takes 2 bytes as a varint */
+        recent_blockhash: blockhash,
+        instructions: vec![],
+        address_table_lookups: vec![],
+    };
+    println!(
+        "v4 noop                    = {}",
+        bincode::serialized_size(&v4_noop).unwrap()
+    );
+    // limit + price
+    let v4_limit_price = v4::Message {
+        compute_budget_header: v4::ComputeBudgetHeader::new(Some(12345), Some(12345), None, None),
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 0,
+        },
+        account_keys: vec![payer], /* same flags as v3, but 12345 takes 2 bytes as a varint */
+        recent_blockhash: blockhash,
+        instructions: vec![],
+        address_table_lookups: vec![],
+    };
+    println!(
+        "v4 with cu limit + price   = {}",
+        bincode::serialized_size(&v4_limit_price).unwrap()
+    );
+    // full
+    let v4_full = v4::Message {
+        compute_budget_header: v4::ComputeBudgetHeader::new(
+            Some(12345),
+            Some(12345),
+            Some(12345),
+            Some(12345),
+        ),
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 0,
+        },
+        account_keys: vec![payer], /* same flags as v3, but 12345 takes 2 bytes as a varint */
+        recent_blockhash: blockhash,
+        instructions: vec![],
+        address_table_lookups: vec![],
+    };
+    println!(
+        "v4 with full cb ix set     = {}",
+        bincode::serialized_size(&v4_full).unwrap()
+    );
+
+    // ————————————————
+    // v5: noop / limit+price / full
+    // ————————————————
+    let price = v5::ScaledPrice::try_from(12345).unwrap();
+    // noop
+    let v5_noop = v5::Message {
+        compute_budget_header: v5::ComputeBudgetHeader::new(None, None, None, None),
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 0,
+        },
+        account_keys: vec![payer], /* same flags as v3, but the price takes 5 bytes */
+        recent_blockhash: blockhash,
+        instructions: vec![],
+        address_table_lookups: vec![],
+    };
+    println!(
+        "v5 noop                    = {}",
+        bincode::serialized_size(&v5_noop).unwrap()
+    );
+    // limit + price
+    let v5_limit_price = v5::Message {
+        compute_budget_header: v5::ComputeBudgetHeader::new(Some(12345), Some(price), None, None),
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 0,
+        },
+        account_keys: vec![payer], /* same flags as v3, but the price takes 5 bytes */
+        recent_blockhash: blockhash,
+        instructions: vec![],
+        address_table_lookups: vec![],
+    };
+    println!(
+        "v5 with cu limit + price   = {}",
+        bincode::serialized_size(&v5_limit_price).unwrap()
+    );
+    // full
+    let v5_full = v5::Message {
+        compute_budget_header: v5::ComputeBudgetHeader::new(
+            Some(12345),
+            Some(price),
+            Some(12345),
+            Some(12345),
+        ),
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 0,
+        },
+        account_keys: vec![payer], /* same flags as v3, but the price takes 5 bytes */
+        recent_blockhash: blockhash,
+        instructions: vec![],
+        address_table_lookups: vec![],
+    };
+    println!(
+        "v5 with full cb ix set     = {}\n",
+        bincode::serialized_size(&v5_full).unwrap()
+    );
+
+    // ————————————————
+    // v3b: noop / limit+price / full
+    // ————————————————
+    // the same sizes as v3, the difference is where a reader finds the budget
+    #[cfg(feature = "v3b")]
+    for (name, v3) in [
+        ("noop                  ", v3_noop),
+        ("with cu limit + price ", v3_limit_price),
+        ("with full cb ix set   ", v3_full),
+    ] {
+        let v3b = v1tx::v3b::Message::from(v3);
+        let size = bincode::serialized_size(&v3b).unwrap();
+        let budget_offset = size as usize - v3b.compute_budget_header.serialized_size();
+        println!("v3b {name} = {size} (budget at byte {budget_offset})");
+    }
 }
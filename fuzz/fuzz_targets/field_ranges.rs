@@ -0,0 +1,36 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use v1tx::{
+    layout::MessageLayout,
+    versioned::{MessageVersion, VersionedMessage},
+};
+
+fuzz_target!(|data: &[u8]| {
+    let Some(&prefix) = data.first() else {
+        return;
+    };
+    let version = if prefix & 0x80 == 0 {
+        Some(MessageVersion::Legacy)
+    } else {
+        MessageVersion::ALL
+            .iter()
+            .copied()
+            .find(|version| version.number() == Some(prefix & 0x7f))
+    };
+    let Some(version) = version else {
+        return;
+    };
+
+    // the layout must never index past the bytes it is given
+    let ranges = MessageLayout::of(version).field_ranges(data);
+    if let Some(ranges) = &ranges {
+        assert!(ranges.iter().all(|(_, range)| range.end <= data.len()));
+    }
+
+    // and must find every field of a message the decoder accepts, where the
+    // encoder put it
+    if let Ok(message) = VersionedMessage::parse(data) {
+        assert_eq!(ranges, Some(MessageLayout::message_field_ranges(&message)));
+    }
+});
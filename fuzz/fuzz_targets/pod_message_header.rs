@@ -0,0 +1,21 @@
+#![no_main]
+
+use bincode::Options;
+use libfuzzer_sys::fuzz_target;
+use v1tx::{v1, v2};
+
+fuzz_target!(|data: &[u8]| {
+    let options = bincode::options()
+        .with_fixint_encoding()
+        .allow_trailing_bytes();
+
+    // reading a header in place must agree with decoding it, for any bytes
+    assert_eq!(
+        v1::PodMessageHeader::from_bytes(data).map(v1::MessageHeader::from),
+        options.deserialize::<v1::MessageHeader>(data).ok()
+    );
+    assert_eq!(
+        v2::PodMessageHeader::from_bytes(data).map(v2::MessageHeader::from),
+        options.deserialize::<v2::MessageHeader>(data).ok()
+    );
+});
@@ -0,0 +1,23 @@
+#![no_main]
+
+use bincode::Options;
+use libfuzzer_sys::fuzz_target;
+use v1tx::v4;
+
+fuzz_target!(|data: &[u8]| {
+    let options = bincode::options()
+        .with_fixint_encoding()
+        .reject_trailing_bytes();
+    let Ok(message) = options.deserialize::<v4::Message>(data) else {
+        return;
+    };
+
+    // anything that decodes must re-encode to the same bytes
+    assert_eq!(options.serialize(&message).unwrap(), data);
+
+    let _ = message.sanitize();
+    let _ = message.compute_budget_request();
+    if let Ok(v0) = message.to_v0() {
+        let _ = v4::Message::from_v0(&v0);
+    }
+});
@@ -0,0 +1,23 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use v1tx::versioned::{MessageVersion, VersionedMessage};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(message) = VersionedMessage::deserialize(data) else {
+        return;
+    };
+
+    // anything that decodes must re-encode to the same bytes
+    assert_eq!(message.serialize(), data);
+
+    if message.sanitize().is_err() {
+        return;
+    }
+    let _ = message.compute_budget_request();
+    for &version in MessageVersion::ALL {
+        if let Ok(converted) = message.convert(version) {
+            let _ = converted.serialize();
+        }
+    }
+});
@@ -0,0 +1,243 @@
+//! Signer and writability queries on the candidate message versions.
+//!
+//! These follow `v0::Message`: writability is what the header requests,
+//! demoted for reserved account keys and for keys invoked as programs (unless
+//! the upgradeable loader is present). On top of that the compute budget
+//! program is never writable. The candidate versions invoke it implicitly
+//! through the header, so it is demoted whether or not an instruction calls it
+//! and without needing the reserved key set.
+
+use std::collections::HashSet;
+
+use solana_message::{
+    MessageHeader, compiled_instruction::CompiledInstruction, v0::MessageAddressTableLookup,
+};
+use solana_pubkey::Pubkey;
+use solana_sdk_ids::bpf_loader_upgradeable;
+
+#[cfg(feature = "v3b")]
+use crate::v3b;
+use crate::{v1, v2, v3, v4, v5};
+
+/// The parts of a message that account roles depend on.
+pub(crate) struct AccountRoles<'a> {
+    pub(crate) header: MessageHeader,
+    pub(crate) account_keys: &'a [Pubkey],
+    pub(crate) instructions: &'a [CompiledInstruction],
+    pub(crate) address_table_lookups: &'a [MessageAddressTableLookup],
+}
+
+impl AccountRoles<'_> {
+    pub(crate) fn is_signer(&self, key_index: usize) -> bool {
+        key_index < usize::from(self.header.num_required_signatures)
+    }
+
+    pub(crate) fn is_key_called_as_program(&self, key_index: usize) -> bool {
+        u8::try_from(key_index).is_ok_and(|key_index| {
+            self.instructions
+                .iter()
+                .any(|ix| ix.program_id_index == key_index)
+        })
+    }
+
+    /// Whether the header requests the account at `key_index` as writable.
+    /// Indexes past the static keys refer to loaded addresses, writable ones
+    /// first.
+    pub(crate) fn is_writable_index(&self, key_index: usize) -> bool {
+        let num_account_keys = self.account_keys.len();
+        let num_signed_accounts = usize::from(self.header.num_required_signatures);
+        if key_index >= num_account_keys {
+            let num_writable_dynamic_addresses: usize = self
+                .address_table_lookups
+                .iter()
+                .map(|lookup| lookup.writable_indexes.len())
+                .sum();
+            key_index - num_account_keys < num_writable_dynamic_addresses
+        } else if key_index >= num_signed_accounts {
+            let num_writable_unsigned_accounts = num_account_keys
+                .saturating_sub(num_signed_accounts)
+                .saturating_sub(usize::from(self.header.num_readonly_unsigned_accounts));
+            key_index - num_signed_accounts < num_writable_unsigned_accounts
+        } else {
+            let num_writable_signed_accounts = num_signed_accounts
+                .saturating_sub(usize::from(self.header.num_readonly_signed_accounts));
+            key_index < num_writable_signed_accounts
+        }
+    }
+
+    fn is_maybe_writable(
+        &self,
+        key_index: usize,
+        reserved_account_keys: Option<&HashSet<Pubkey>>,
+    ) -> bool {
+        self.is_writable(
+            key_index,
+            self.account_keys.get(key_index),
+            reserved_account_keys,
+            self.account_keys.contains(&bpf_loader_upgradeable::id()),
+        )
+    }
+
+    /// Whether the account at `key_index`, whose key is `key` if known, keeps
+    /// its requested write lock after demotion.
+    pub(crate) fn is_writable(
+        &self,
+        key_index: usize,
+        key: Option<&Pubkey>,
+        reserved_account_keys: Option<&HashSet<Pubkey>>,
+        is_upgradeable_loader_present: bool,
+    ) -> bool {
+        let is_reserved = key
+            .zip(reserved_account_keys)
+            .is_some_and(|(key, reserved)| reserved.contains(key));
+        let is_compute_budget_program = key.is_some_and(solana_compute_budget_interface::check_id);
+        let is_demoted_program =
+            self.is_key_called_as_program(key_index) && !is_upgradeable_loader_present;
+
+        self.is_writable_index(key_index)
+            && !is_reserved
+            && !is_compute_budget_program
+            && !is_demoted_program
+    }
+}
+
+/// The account role queries for the candidate versions. The v1 and v2 headers
+/// convert into the `MessageHeader` they carry.
+macro_rules! impl_account_roles {
+    ($($version:ident),*) => {$(
+        impl $version::Message {
+            pub(crate) fn account_roles(&self) -> AccountRoles<'_> {
+                AccountRoles {
+                    header: self.header.into(),
+                    account_keys: &self.account_keys,
+                    instructions: &self.instructions,
+                    address_table_lookups: &self.address_table_lookups,
+                }
+            }
+
+            pub fn static_account_keys(&self) -> &[Pubkey] {
+                &self.account_keys
+            }
+
+            /// Whether the account at `key_index` must sign the message.
+            pub fn is_signer(&self, key_index: usize) -> bool {
+                self.account_roles().is_signer(key_index)
+            }
+
+            /// Whether the account at `key_index` is invoked by any instruction.
+            pub fn is_key_called_as_program(&self, key_index: usize) -> bool {
+                self.account_roles().is_key_called_as_program(key_index)
+            }
+
+            /// Whether the account at `key_index` may be write locked. Loaded addresses
+            /// can't be checked against `reserved_account_keys` before they are
+            /// loaded, so this is an upper bound.
+            pub fn is_maybe_writable(
+                &self,
+                key_index: usize,
+                reserved_account_keys: Option<&HashSet<Pubkey>>,
+            ) -> bool {
+                self.account_roles()
+                    .is_maybe_writable(key_index, reserved_account_keys)
+            }
+        }
+    )*};
+}
+
+impl_account_roles!(v1, v2, v3, v4, v5);
+#[cfg(feature = "v3b")]
+impl_account_roles!(v3b);
+
+#[test]
+fn test_account_roles() {
+    use solana_hash::Hash;
+    use solana_message::v0;
+
+    let reserved = Pubkey::new_unique();
+    let message = v0::Message {
+        header: MessageHeader {
+            num_required_signatures: 2,
+            num_readonly_signed_accounts: 1,
+            num_readonly_unsigned_accounts: 0,
+        },
+        account_keys: vec![
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            reserved,
+            // requested writable but never invoked
+            solana_compute_budget_interface::ID,
+            // requested writable but demoted as a program
+            Pubkey::new_unique(),
+        ],
+        recent_blockhash: Hash::new_unique(),
+        instructions: vec![CompiledInstruction::new_from_raw_parts(
+            5,
+            vec![],
+            vec![0, 2, 4],
+        )],
+        address_table_lookups: vec![MessageAddressTableLookup {
+            account_key: Pubkey::new_unique(),
+            writable_indexes: vec![0],
+            readonly_indexes: vec![1],
+        }],
+    };
+    let reserved_account_keys = HashSet::from([reserved]);
+
+    // (is_signer, is_key_called_as_program, is_maybe_writable) for each
+    // static key, then the writable and readonly loaded addresses
+    let expected = vec![
+        (true, false, true),
+        (true, false, false),
+        (false, false, true),
+        (false, false, false),
+        (false, false, false),
+        (false, true, false),
+        (false, false, true),
+        (false, false, false),
+    ];
+
+    // without compute budget instructions the conversions keep every key
+    let v1 = v1::Message::from_v0(&message);
+    let v2 = v2::Message::from_v0(&message);
+    let v3 = v3::Message::from_v0(&message);
+    assert_eq!(v1.static_account_keys(), message.account_keys);
+    assert_eq!(v2.static_account_keys(), message.account_keys);
+    assert_eq!(v3.static_account_keys(), message.account_keys);
+
+    let reserved_account_keys = Some(&reserved_account_keys);
+    let roles = |f: &dyn Fn(usize) -> (bool, bool, bool)| (0..expected.len()).map(f).collect();
+    let v1_roles: Vec<_> = roles(&|i| {
+        (
+            v1.is_signer(i),
+            v1.is_key_called_as_program(i),
+            v1.is_maybe_writable(i, reserved_account_keys),
+        )
+    });
+    let v2_roles: Vec<_> = roles(&|i| {
+        (
+            v2.is_signer(i),
+            v2.is_key_called_as_program(i),
+            v2.is_maybe_writable(i, reserved_account_keys),
+        )
+    });
+    let v3_roles: Vec<_> = roles(&|i| {
+        (
+            v3.is_signer(i),
+            v3.is_key_called_as_program(i),
+            v3.is_maybe_writable(i, reserved_account_keys),
+        )
+    });
+    assert_eq!(v1_roles, expected);
+    assert_eq!(v2_roles, expected);
+    assert_eq!(v3_roles, expected);
+
+    // reserved keys are only demoted when the set is known
+    assert!(v3.is_maybe_writable(3, None));
+    assert!(!v3.is_maybe_writable(4, None));
+
+    // a called program keeps its write lock alongside the upgradeable loader
+    let mut upgradeable = v3;
+    upgradeable.account_keys[2] = bpf_loader_upgradeable::id();
+    assert!(upgradeable.is_maybe_writable(5, None));
+}
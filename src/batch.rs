@@ -0,0 +1,104 @@
+//! Parse and sanitize a batch of serialized messages in one call.
+//!
+//! [`BatchParser`] owns the per-packet result buffer, so an ingestion loop can
+//! keep a single parser around and avoid reallocating it for every batch. That
+//! buffer is the only thing reused: each decoded message still allocates its
+//! own account keys, instructions and lookups.
+
+use crate::versioned::{ParseError, VersionedMessage};
+
+pub type PacketResult = Result<VersionedMessage, ParseError>;
+
+#[derive(Debug, Default)]
+pub struct BatchParser {
+    results: Vec<PacketResult>,
+}
+
+impl BatchParser {
+    pub fn new() -> BatchParser {
+        BatchParser::default()
+    }
+
+    /// Create a parser whose result buffer already fits `batch_size` packets.
+    pub fn with_capacity(batch_size: usize) -> BatchParser {
+        BatchParser {
+            results: Vec::with_capacity(batch_size),
+        }
+    }
+
+    /// Parse and sanitize every packet, returning one result per packet in
+    /// input order. The returned slice is overwritten by the next call.
+    pub fn parse<'a, I>(&mut self, packets: I) -> &[PacketResult]
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        self.results.clear();
+        self.results
+            .extend(packets.into_iter().map(VersionedMessage::parse));
+        &self.results
+    }
+
+    /// Parse packets stored back to back in `buffer`. Packet `i` spans
+    /// `buffer[offsets[i]..offsets[i + 1]]`, so `offsets` holds one more entry
+    /// than there are packets. Packets whose bounds are out of range or
+    /// decreasing are reported as [`ParseError::InvalidEncoding`].
+    pub fn parse_contiguous(&mut self, buffer: &[u8], offsets: &[usize]) -> &[PacketResult] {
+        self.results.clear();
+        self.results.extend(offsets.windows(2).map(|bounds| {
+            let packet = buffer
+                .get(bounds[0]..bounds[1])
+                .ok_or(ParseError::InvalidEncoding)?;
+            VersionedMessage::parse(packet)
+        }));
+        &self.results
+    }
+}
+
+#[test]
+fn test_parse_batch() {
+    use solana_hash::Hash;
+    use solana_message::MessageHeader;
+    use solana_pubkey::Pubkey;
+    use solana_sanitize::SanitizeError;
+
+    use crate::v3;
+
+    let mut message = v3::Message {
+        compute_budget_header: v3::ComputeBudgetHeader::new(Some(12), None, None, Some(34)),
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 0,
+        },
+        account_keys: vec![Pubkey::new_unique()],
+        recent_blockhash: Hash::new_unique(),
+        instructions: vec![],
+        address_table_lookups: vec![],
+    };
+    let valid = VersionedMessage::V3(message.clone()).serialize();
+    message.header.num_readonly_signed_accounts = 1;
+    let unsanitary = VersionedMessage::V3(message).serialize();
+    let truncated = &valid[..valid.len() - 1];
+
+    let mut parser = BatchParser::with_capacity(3);
+    let results = parser.parse([&valid[..], &unsanitary[..], truncated]);
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    assert_eq!(
+        results[1],
+        Err(ParseError::Sanitize(SanitizeError::InvalidValue))
+    );
+    assert_eq!(results[2], Err(ParseError::InvalidEncoding));
+
+    let mut buffer = valid.clone();
+    buffer.extend_from_slice(&unsanitary);
+    let offsets = [0, valid.len(), buffer.len(), buffer.len() + 1];
+    let results = parser.parse_contiguous(&buffer, &offsets);
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    assert_eq!(
+        results[1],
+        Err(ParseError::Sanitize(SanitizeError::InvalidValue))
+    );
+    assert_eq!(results[2], Err(ParseError::InvalidEncoding));
+}
@@ -0,0 +1,100 @@
+//! Convert serialized messages between versions, one per input line.
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use v1tx::{
+    compute_budget::decode_compute_budget_instruction,
+    convert::compute_budget_instruction_indexes,
+    versioned::{MessageVersion, VersionedMessage},
+};
+
+use crate::input::{self, Encoding, Payload};
+
+/// Convert every non-empty line of `text` to `version`, printing the encoded
+/// result on stdout and a size report on stderr.
+pub fn run(
+    text: &str,
+    version: MessageVersion,
+    encoding: Option<Encoding>,
+    output_encoding: Encoding,
+) -> Result<(), String> {
+    let mut total_before = 0;
+    let mut total_after = 0;
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+
+    for (line_number, line) in lines.iter().enumerate() {
+        let context = |err: String| format!("line {}: {err}", line_number + 1);
+        let payload = input::decode_payload(line, encoding).map_err(context)?;
+        if let Payload::Transaction(_) = payload {
+            eprintln!(
+                "line {}: converting the message of a transaction, signatures are dropped",
+                line_number + 1
+            );
+        }
+
+        let message = payload.message();
+        let converted = message
+            .convert(version)
+            .map_err(|err| context(err.to_string()))?;
+        let before = message.serialize().len();
+        let after = converted.serialize().len();
+        total_before += before;
+        total_after += after;
+
+        println!("{}", encode(&converted.serialize(), output_encoding));
+        eprintln!(
+            "line {}: {} -> {}: {before} -> {after} bytes ({} saved)",
+            line_number + 1,
+            message.version(),
+            converted.version(),
+            before as isize - after as isize,
+        );
+        report_unfolded(line_number, &converted);
+    }
+
+    if lines.len() > 1 {
+        eprintln!(
+            "total: {total_before} -> {total_after} bytes ({} saved)",
+            total_before as isize - total_after as isize
+        );
+    }
+    Ok(())
+}
+
+/// Compute budget instructions that are still instructions after converting
+/// to a version with a compute budget header.
+fn report_unfolded(line_number: usize, message: &VersionedMessage) {
+    if matches!(
+        message.version(),
+        MessageVersion::Legacy | MessageVersion::V0
+    ) {
+        return;
+    }
+
+    let indexes =
+        compute_budget_instruction_indexes(message.static_account_keys(), message.instructions());
+    for index in indexes {
+        let data = &message.instructions()[index].data;
+        match decode_compute_budget_instruction(data) {
+            Some(instruction) => eprintln!(
+                "line {}: instruction {index} not folded into the header: {instruction:?}",
+                line_number + 1
+            ),
+            None => eprintln!(
+                "line {}: instruction {index} not folded into the header: invalid data {}",
+                line_number + 1,
+                hex::encode(data)
+            ),
+        }
+    }
+}
+
+fn encode(bytes: &[u8], encoding: Encoding) -> String {
+    match encoding {
+        Encoding::Base58 => bs58::encode(bytes).into_string(),
+        Encoding::Base64 => BASE64_STANDARD.encode(bytes),
+        Encoding::Hex => hex::encode(bytes),
+    }
+}
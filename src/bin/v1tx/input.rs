@@ -0,0 +1,112 @@
+//! Reading and decoding serialized transactions and messages.
+
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use clap::ValueEnum;
+use v1tx::{transaction::VersionedTransaction, versioned::VersionedMessage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    Base58,
+    Base64,
+    Hex,
+}
+
+/// Read the whole input from `path`, or stdin if there is none.
+pub fn read_input(path: Option<&Path>) -> io::Result<String> {
+    match path {
+        Some(path) => fs::read_to_string(path),
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            Ok(input)
+        }
+    }
+}
+
+/// Decode text with `encoding`, or guess it: hex first, then base58, then
+/// base64. Hex strings nearly always contain a `0`, which base58 excludes.
+pub fn decode(text: &str, encoding: Option<Encoding>) -> Result<Vec<u8>, String> {
+    let text = text.trim();
+    let encoding = encoding.unwrap_or_else(|| {
+        if text.len().is_multiple_of(2) && text.bytes().all(|b| b.is_ascii_hexdigit()) {
+            Encoding::Hex
+        } else if bs58::decode(text).into_vec().is_ok() {
+            Encoding::Base58
+        } else {
+            Encoding::Base64
+        }
+    });
+    decode_as(text, encoding)
+}
+
+fn decode_as(text: &str, encoding: Encoding) -> Result<Vec<u8>, String> {
+    match encoding {
+        Encoding::Base58 => bs58::decode(text).into_vec().map_err(|err| err.to_string()),
+        Encoding::Base64 => BASE64_STANDARD.decode(text).map_err(|err| err.to_string()),
+        Encoding::Hex => hex::decode(text).map_err(|err| err.to_string()),
+    }
+}
+
+pub enum Payload {
+    Transaction(VersionedTransaction),
+    Message(VersionedMessage),
+}
+
+impl Payload {
+    pub fn message(&self) -> &VersionedMessage {
+        match self {
+            Payload::Transaction(transaction) => &transaction.message,
+            Payload::Message(message) => message,
+        }
+    }
+}
+
+/// Decode `bytes` as a transaction or a bare message, preferring whichever
+/// also sanitizes. Unsanitary payloads are still returned so they can be
+/// inspected.
+pub fn parse_payload(bytes: &[u8]) -> Result<Payload, String> {
+    let transaction = VersionedTransaction::deserialize(bytes);
+    let message = VersionedMessage::deserialize(bytes);
+    match (transaction, message) {
+        (Ok(transaction), _) if transaction.sanitize().is_ok() => {
+            Ok(Payload::Transaction(transaction))
+        }
+        (_, Ok(message)) => Ok(Payload::Message(message)),
+        (Ok(transaction), Err(_)) => Ok(Payload::Transaction(transaction)),
+        (Err(_), Err(err)) => Err(format!("not a transaction or message: {err}")),
+    }
+}
+
+/// Decode and parse text with `encoding`. Without one, every encoding the
+/// text is valid in is tried in the order [`decode`] guesses them, since
+/// short base64 strings are often valid base58 as well.
+pub fn decode_payload(text: &str, encoding: Option<Encoding>) -> Result<Payload, String> {
+    if encoding.is_some() {
+        return parse_payload(&decode(text, encoding)?);
+    }
+
+    [Encoding::Hex, Encoding::Base58, Encoding::Base64]
+        .into_iter()
+        .filter_map(|encoding| decode_as(text.trim(), encoding).ok())
+        .find_map(|bytes| parse_payload(&bytes).ok())
+        .map_or_else(|| parse_payload(&decode(text, None)?), Ok)
+}
+
+#[test]
+fn test_decode_detects_encoding() {
+    let bytes = vec![0x83, 0x00, 0xff, 0x10];
+    assert_eq!(decode("8300ff10\n", None), Ok(bytes.clone()));
+    assert_eq!(
+        decode(&bs58::encode(&bytes).into_string(), None),
+        Ok(bytes.clone())
+    );
+    assert_eq!(decode(&BASE64_STANDARD.encode(&bytes), None), Ok(bytes));
+    // an explicit encoding is never second-guessed
+    assert!(decode("8300ff10", Some(Encoding::Base58)).is_err());
+}
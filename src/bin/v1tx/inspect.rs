@@ -0,0 +1,303 @@
+//! Human-readable breakdown of a transaction or message, with the byte range
+//! each section occupies in the input.
+
+use std::ops::Range;
+
+use solana_message::{MessageHeader, compiled_instruction::CompiledInstruction};
+use solana_short_vec::ShortU16;
+use v1tx::{
+    compute_budget::ComputeBudgetHeaderFields,
+    transaction::VersionedTransaction,
+    versioned::{MessageVersion, VersionedMessage},
+};
+
+use crate::input::Payload;
+
+/// Walks the input, handing out the byte range of each section in order.
+struct Cursor {
+    offset: usize,
+}
+
+impl Cursor {
+    fn take(&mut self, len: usize) -> Range<usize> {
+        let range = self.offset..self.offset + len;
+        self.offset = range.end;
+        range
+    }
+
+    fn take_short_vec_len(&mut self, len: usize) -> Range<usize> {
+        self.take(serialized_size(&ShortU16(len as u16)))
+    }
+}
+
+fn serialized_size<T: serde::Serialize>(value: &T) -> usize {
+    bincode::serialized_size(value).unwrap() as usize
+}
+
+fn fmt_range(range: &Range<usize>) -> String {
+    format!("[{}..{}]", range.start, range.end)
+}
+
+pub fn print(payload: &Payload) {
+    let mut cursor = Cursor { offset: 0 };
+    match payload {
+        Payload::Transaction(transaction) => {
+            println!("kind: transaction");
+            println!("size: {} bytes", transaction.serialize().len());
+            print_sanitize(transaction.sanitize());
+            print_signatures(&mut cursor, transaction);
+        }
+        Payload::Message(message) => {
+            println!("kind: message");
+            println!("size: {} bytes", message.serialize().len());
+            print_sanitize(message.sanitize());
+        }
+    }
+    print_message(&mut cursor, payload.message());
+}
+
+fn print_sanitize(result: Result<(), solana_sanitize::SanitizeError>) {
+    match result {
+        Ok(()) => println!("sanitize: ok"),
+        Err(err) => println!("sanitize: failed ({err})"),
+    }
+}
+
+fn print_signatures(cursor: &mut Cursor, transaction: &VersionedTransaction) {
+    let len = cursor.take_short_vec_len(transaction.signatures.len());
+    println!(
+        "\nsignatures ({}) {}",
+        transaction.signatures.len(),
+        fmt_range(&len)
+    );
+    for (index, signature) in transaction.signatures.iter().enumerate() {
+        let range = cursor.take(64);
+        println!("  [{index}] {} {signature}", fmt_range(&range));
+    }
+}
+
+fn print_message(cursor: &mut Cursor, message: &VersionedMessage) {
+    println!("\nmessage version: {}", message.version());
+    if let Some(number) = message.version().number() {
+        let range = cursor.take(1);
+        println!("  prefix {} {:#04x}", fmt_range(&range), 0x80 | number);
+    }
+
+    match message {
+        VersionedMessage::V1(message) => {
+            let range = cursor.take(serialized_size(&message.header));
+            println!("  header {}", fmt_range(&range));
+            println!(
+                "    compute_unit_price: {}",
+                message.header.compute_unit_price
+            );
+            println!(
+                "    compute_unit_limit: {}",
+                message.header.compute_unit_limit
+            );
+        }
+        VersionedMessage::V2(message) => {
+            let range = cursor.take(serialized_size(&message.header));
+            println!("  header {}", fmt_range(&range));
+            println!(
+                "    compute_unit_price: {}",
+                message.header.compute_unit_price
+            );
+            println!(
+                "    compute_unit_limit: {}",
+                message.header.compute_unit_limit
+            );
+            println!(
+                "    loaded_accounts_data_limit: {}",
+                message.header.loaded_accounts_data_limit
+            );
+            println!(
+                "    requested_heap_bytes: {}",
+                message.header.requested_heap_bytes
+            );
+        }
+        _ => {
+            // v3b carries its compute budget after everything else
+            let leading_budget = message
+                .compute_budget_header()
+                .filter(|_| message.version() != MessageVersion::V3b);
+            if let Some(header) = leading_budget {
+                print_compute_budget_header(cursor, header);
+            }
+            let range = cursor.take(3);
+            println!("  header {}", fmt_range(&range));
+        }
+    }
+    let header = message.header();
+    println!(
+        "    num_required_signatures: {}",
+        header.num_required_signatures
+    );
+    println!(
+        "    num_readonly_signed_accounts: {}",
+        header.num_readonly_signed_accounts
+    );
+    println!(
+        "    num_readonly_unsigned_accounts: {}",
+        header.num_readonly_unsigned_accounts
+    );
+
+    print_account_keys(cursor, message, &header);
+
+    let range = cursor.take(32);
+    println!(
+        "  recent_blockhash {} {}",
+        fmt_range(&range),
+        message.recent_blockhash()
+    );
+
+    print_instructions(cursor, message);
+
+    if message.version() != MessageVersion::Legacy {
+        print_address_table_lookups(cursor, message);
+    }
+
+    if message.version() == MessageVersion::V3b {
+        print_compute_budget_header(cursor, message.compute_budget_header().unwrap());
+    }
+}
+
+/// Print a v3, v3b, v4 or v5 compute budget header.
+fn print_compute_budget_header(cursor: &mut Cursor, header: &dyn ComputeBudgetHeaderFields) {
+    let range = cursor.take(header.serialized_size());
+    println!("  compute_budget_header {}", fmt_range(&range));
+    let flags = header.flags();
+    let flag_names: Vec<&str> = flags.iter_names().map(|(name, _)| name).collect();
+    println!(
+        "    flags: {:#010b} ({})",
+        flags.bits(),
+        flag_names.join(" | ")
+    );
+    let names = [
+        "compute_unit_limit",
+        "compute_unit_price",
+        "loaded_accounts_data_limit",
+        "requested_heap_bytes_limit",
+        "max_fee_lamports",
+    ];
+    let request = header.compute_budget_request();
+    let values = [
+        request.compute_unit_limit.map(u64::from),
+        request.compute_unit_price,
+        request.loaded_accounts_data_limit.map(u64::from),
+        request.requested_heap_bytes.map(u64::from),
+        request.max_fee_lamports,
+    ];
+    for (name, value) in names.into_iter().zip(values) {
+        match value {
+            Some(value) => println!("    {name}: {value}"),
+            None => println!("    {name}: (not set)"),
+        }
+    }
+}
+
+fn print_account_keys(cursor: &mut Cursor, message: &VersionedMessage, header: &MessageHeader) {
+    let account_keys = message.static_account_keys();
+    let len = cursor.take_short_vec_len(account_keys.len());
+    println!(
+        "  account_keys ({}) {}",
+        account_keys.len(),
+        fmt_range(&len)
+    );
+
+    let num_signers = usize::from(header.num_required_signatures);
+    let num_writable_signers =
+        num_signers.saturating_sub(usize::from(header.num_readonly_signed_accounts));
+    let num_writable_unsigned = account_keys
+        .len()
+        .saturating_sub(usize::from(header.num_readonly_unsigned_accounts));
+    for (index, key) in account_keys.iter().enumerate() {
+        let range = cursor.take(32);
+        let is_signer = index < num_signers;
+        let is_writable = if is_signer {
+            index < num_writable_signers
+        } else {
+            index < num_writable_unsigned
+        };
+        let mut roles = vec![
+            if is_signer { "signer" } else { "unsigned" },
+            if is_writable { "writable" } else { "readonly" },
+        ];
+        if index == 0 {
+            roles.push("fee payer");
+        }
+        println!(
+            "    [{index}] {} {key} ({})",
+            fmt_range(&range),
+            roles.join(", ")
+        );
+    }
+}
+
+fn print_instructions(cursor: &mut Cursor, message: &VersionedMessage) {
+    let instructions = message.instructions();
+    let len = cursor.take_short_vec_len(instructions.len());
+    println!(
+        "  instructions ({}) {}",
+        instructions.len(),
+        fmt_range(&len)
+    );
+    for (index, instruction) in instructions.iter().enumerate() {
+        let range = cursor.take(serialized_size(instruction));
+        print_instruction(index, &range, message, instruction);
+    }
+}
+
+fn print_instruction(
+    index: usize,
+    range: &Range<usize>,
+    message: &VersionedMessage,
+    instruction: &CompiledInstruction,
+) {
+    let program_id = message
+        .static_account_keys()
+        .get(usize::from(instruction.program_id_index))
+        .map_or_else(|| "(out of range)".to_string(), ToString::to_string);
+    println!("    [{index}] {}", fmt_range(range));
+    println!(
+        "      program: [{}] {program_id}",
+        instruction.program_id_index
+    );
+    println!("      accounts: {:?}", instruction.accounts);
+    println!(
+        "      data ({} bytes): {}",
+        instruction.data.len(),
+        hex::encode(&instruction.data)
+    );
+}
+
+fn print_address_table_lookups(cursor: &mut Cursor, message: &VersionedMessage) {
+    let lookups = message.address_table_lookups().unwrap_or_default();
+    let len = cursor.take_short_vec_len(lookups.len());
+    println!(
+        "  address_table_lookups ({}) {}",
+        lookups.len(),
+        fmt_range(&len)
+    );
+
+    // loaded keys are indexed after the static keys: all writable loads
+    // first, then all readonly loads
+    let mut next_writable = message.static_account_keys().len();
+    let mut next_readonly = next_writable
+        + lookups
+            .iter()
+            .map(|lookup| lookup.writable_indexes.len())
+            .sum::<usize>();
+    for (index, lookup) in lookups.iter().enumerate() {
+        let range = cursor.take(serialized_size(lookup));
+        println!("    [{index}] {} {}", fmt_range(&range), lookup.account_key);
+        for table_index in &lookup.writable_indexes {
+            println!("      [{next_writable}] table[{table_index}] (writable)");
+            next_writable += 1;
+        }
+        for table_index in &lookup.readonly_indexes {
+            println!("      [{next_readonly}] table[{table_index}] (readonly)");
+            next_readonly += 1;
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Command-line tools for the candidate message formats.
+
+mod convert;
+mod input;
+mod inspect;
+mod report;
+
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::{Parser, Subcommand};
+use v1tx::versioned::MessageVersion;
+
+use crate::input::Encoding;
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print a breakdown of a serialized transaction or message.
+    ///
+    /// The version (legacy, v0 to v5 or v3b) and whether the input is a
+    /// signed transaction or a bare message are detected automatically.
+    Inspect {
+        /// File to read, or stdin if omitted.
+        path: Option<PathBuf>,
+
+        /// Input encoding. Guessed from the input if omitted.
+        #[arg(long, value_enum)]
+        encoding: Option<Encoding>,
+    },
+    /// Convert messages to another version, one per input line.
+    ///
+    /// Compute budget instructions are folded into the target's header where
+    /// it can express them. The converted messages are written to stdout, and
+    /// the bytes saved and any compute budget instructions left unfolded are
+    /// reported on stderr.
+    Convert {
+        /// File to read, or stdin if omitted.
+        path: Option<PathBuf>,
+
+        /// Version to convert to: legacy, v0 to v5 or v3b.
+        #[arg(long)]
+        to: MessageVersion,
+
+        /// Input encoding. Guessed from each line if omitted.
+        #[arg(long, value_enum)]
+        encoding: Option<Encoding>,
+
+        /// Output encoding.
+        #[arg(long, value_enum, default_value = "base64")]
+        output_encoding: Encoding,
+    },
+    /// Measure the bytes each candidate version saves over a corpus of
+    /// transactions, one per input line.
+    ///
+    /// Writes one CSV row per transaction to stdout and a summary of the
+    /// savings and compute budget usage to stderr.
+    Report {
+        /// File to read, or stdin if omitted.
+        path: Option<PathBuf>,
+
+        /// Input encoding. Guessed from each line if omitted.
+        #[arg(long, value_enum)]
+        encoding: Option<Encoding>,
+    },
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    match cli.command {
+        Command::Inspect { path, encoding } => {
+            let text = input::read_input(path.as_deref()).map_err(|err| err.to_string())?;
+            let payload = input::decode_payload(&text, encoding)?;
+            inspect::print(&payload);
+            Ok(())
+        }
+        Command::Convert {
+            path,
+            to,
+            encoding,
+            output_encoding,
+        } => {
+            let text = input::read_input(path.as_deref()).map_err(|err| err.to_string())?;
+            convert::run(&text, to, encoding, output_encoding)
+        }
+        Command::Report { path, encoding } => {
+            let text = input::read_input(path.as_deref()).map_err(|err| err.to_string())?;
+            report::run(&text, encoding)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
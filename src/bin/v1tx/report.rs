@@ -0,0 +1,186 @@
+//! Size savings of the candidate versions over a corpus of real transactions.
+
+use v1tx::{
+    compute_budget::ComputeBudgetRequest,
+    versioned::{MessageVersion, VersionedMessage},
+};
+
+use crate::input::{self, Encoding, Payload};
+
+const TARGETS: [MessageVersion; 6] = [
+    MessageVersion::V1,
+    MessageVersion::V2,
+    MessageVersion::V3,
+    MessageVersion::V4,
+    MessageVersion::V5,
+    MessageVersion::V3b,
+];
+
+/// Bytes a transaction must save before it has room for one more static
+/// account key.
+const ACCOUNT_KEY_SIZE: isize = 32;
+
+struct Row {
+    version: MessageVersion,
+    size: usize,
+    /// Bytes saved converting to each of `TARGETS`, negative if it grew.
+    saved: [isize; TARGETS.len()],
+    /// `None` if the compute budget instructions don't process cleanly.
+    request: Option<ComputeBudgetRequest>,
+}
+
+/// Convert every non-empty line of `text` to each candidate version, printing
+/// one CSV row per line on stdout and an aggregate summary on stderr.
+pub fn run(text: &str, encoding: Option<Encoding>) -> Result<(), String> {
+    println!(
+        "line,version,size,v1_saved,v2_saved,v3_saved,v4_saved,v5_saved,v3b_saved,compute_unit_limit,compute_unit_price,\
+         loaded_accounts_data_limit,requested_heap_bytes"
+    );
+
+    let mut rows = Vec::new();
+    for (line_number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = line_number + 1;
+        let row = match measure(line, encoding) {
+            Ok(row) => row,
+            Err(err) => {
+                eprintln!("line {line_number}: skipped: {err}");
+                continue;
+            }
+        };
+
+        let request = row.request.unwrap_or_default();
+        let fmt_field = |value: Option<u64>| value.map_or_else(String::new, |v| v.to_string());
+        println!(
+            "{line_number},{},{},{},{},{},{},{},{},{},{},{},{}",
+            row.version,
+            row.size,
+            row.saved[0],
+            row.saved[1],
+            row.saved[2],
+            row.saved[3],
+            row.saved[4],
+            row.saved[5],
+            fmt_field(request.compute_unit_limit.map(u64::from)),
+            fmt_field(request.compute_unit_price),
+            fmt_field(request.loaded_accounts_data_limit.map(u64::from)),
+            fmt_field(request.requested_heap_bytes.map(u64::from)),
+        );
+        rows.push(row);
+    }
+
+    print_summary(&rows);
+    Ok(())
+}
+
+fn measure(line: &str, encoding: Option<Encoding>) -> Result<Row, String> {
+    let payload = input::decode_payload(line, encoding)?;
+    let message = payload.message();
+    let size = match &payload {
+        Payload::Transaction(transaction) => transaction.serialize().len(),
+        Payload::Message(message) => message.serialize().len(),
+    };
+
+    // signatures are unaffected by the message version, so the message size
+    // difference is the transaction size difference
+    let message_size = message.serialize().len() as isize;
+    let mut saved = [0; TARGETS.len()];
+    for (saved, version) in saved.iter_mut().zip(TARGETS) {
+        let converted: VersionedMessage =
+            message.convert(version).map_err(|err| err.to_string())?;
+        *saved = message_size - converted.serialize().len() as isize;
+    }
+
+    Ok(Row {
+        version: message.version(),
+        size,
+        saved,
+        request: message.compute_budget_request().ok(),
+    })
+}
+
+fn print_summary(rows: &[Row]) {
+    eprintln!("transactions: {}", rows.len());
+    if rows.is_empty() {
+        return;
+    }
+
+    let total = rows.len() as f64;
+    let fraction = |count: usize| 100.0 * count as f64 / total;
+    for &version in MessageVersion::ALL {
+        let count = rows.iter().filter(|row| row.version == version).count();
+        if count == 0 {
+            continue;
+        }
+        eprintln!("  {version}: {count} ({:.1}%)", fraction(count));
+    }
+
+    eprintln!("\ncompute budget usage:");
+    let names = [
+        "compute_unit_limit",
+        "compute_unit_price",
+        "loaded_accounts_data_limit",
+        "requested_heap_bytes",
+    ];
+    for (index, name) in names.into_iter().enumerate() {
+        let count = rows
+            .iter()
+            .filter(|row| row.request.as_ref().is_some_and(|r| requested(r)[index]))
+            .count();
+        eprintln!("  {name}: {count} ({:.1}%)", fraction(count));
+    }
+    let invalid = rows.iter().filter(|row| row.request.is_none()).count();
+    eprintln!("  invalid: {invalid} ({:.1}%)", fraction(invalid));
+
+    for (index, version) in TARGETS.iter().enumerate() {
+        let mut saved: Vec<isize> = rows.iter().map(|row| row.saved[index]).collect();
+        saved.sort_unstable();
+        let mean = saved.iter().sum::<isize>() as f64 / total;
+        let smaller = saved.iter().filter(|&&saved| saved > 0).count();
+        let room_for_key = saved
+            .iter()
+            .filter(|&&saved| saved >= ACCOUNT_KEY_SIZE)
+            .count();
+
+        eprintln!("\n{version} bytes saved:");
+        eprintln!(
+            "  mean {mean:.1}, median {}, p99 {}, min {}, max {}",
+            percentile(&saved, 50),
+            percentile(&saved, 99),
+            saved[0],
+            saved[saved.len() - 1],
+        );
+        eprintln!("  smaller: {smaller} ({:.1}%)", fraction(smaller));
+        eprintln!(
+            "  room for another account key: {room_for_key} ({:.1}%)",
+            fraction(room_for_key)
+        );
+    }
+}
+
+fn requested(request: &ComputeBudgetRequest) -> [bool; 4] {
+    [
+        request.compute_unit_limit.is_some(),
+        request.compute_unit_price.is_some(),
+        request.loaded_accounts_data_limit.is_some(),
+        request.requested_heap_bytes.is_some(),
+    ]
+}
+
+/// Nearest-rank percentile of a sorted, non-empty slice.
+fn percentile(sorted: &[isize], percent: usize) -> isize {
+    let rank = (percent * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[test]
+fn test_percentile() {
+    let values: Vec<isize> = (1..=100).collect();
+    assert_eq!(percentile(&values, 50), 50);
+    assert_eq!(percentile(&values, 99), 99);
+    assert_eq!(percentile(&values, 100), 100);
+    assert_eq!(percentile(&[7], 99), 7);
+    assert_eq!(percentile(&[1, 2, 3], 50), 2);
+}
@@ -0,0 +1,196 @@
+//! The runtime's default compute unit limit for messages that don't request
+//! one.
+//!
+//! Once `reserve_minimal_cus_for_builtin_instructions` is active, builtin
+//! instructions only reserve [`MAX_BUILTIN_ALLOCATION_COMPUTE_UNIT_LIMIT`] and
+//! other instructions keep [`DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT`]. Builtins
+//! migrated to BPF count as other instructions once their migration feature is
+//! active. Before that every instruction except compute budget ones gets the
+//! default.
+
+use solana_pubkey::Pubkey;
+use solana_sdk_ids::{
+    address_lookup_table, bpf_loader, bpf_loader_deprecated, bpf_loader_upgradeable,
+    compute_budget, config, ed25519_program, loader_v4, secp256k1_program, secp256r1_program,
+    stake, system_program, vote, zk_elgamal_proof_program, zk_token_proof_program,
+};
+
+use crate::compute_budget::ComputeBudgetRequest;
+
+/// Largest compute unit limit a transaction can request.
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+/// Compute units reserved for each non-builtin instruction when no limit is
+/// requested.
+pub const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+/// Compute units reserved for each builtin instruction when no limit is
+/// requested, once builtins reserve their own minimum.
+pub const MAX_BUILTIN_ALLOCATION_COMPUTE_UNIT_LIMIT: u32 = 3_000;
+
+/// The features the default compute unit limit depends on. The default has
+/// none of them active.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureSet {
+    pub reserve_minimal_cus_for_builtin_instructions: bool,
+    pub migrate_stake_program_to_core_bpf: bool,
+    pub migrate_config_program_to_core_bpf: bool,
+    pub migrate_address_lookup_table_program_to_core_bpf: bool,
+}
+
+impl FeatureSet {
+    pub fn all_enabled() -> Self {
+        Self {
+            reserve_minimal_cus_for_builtin_instructions: true,
+            migrate_stake_program_to_core_bpf: true,
+            migrate_config_program_to_core_bpf: true,
+            migrate_address_lookup_table_program_to_core_bpf: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgramKind {
+    NotBuiltin,
+    Builtin,
+    /// A builtin that becomes a BPF program once its feature is active.
+    MigratingBuiltin {
+        migrated: bool,
+    },
+}
+
+impl ProgramKind {
+    pub fn classify(program_id: &Pubkey, feature_set: &FeatureSet) -> Self {
+        const BUILTINS: [Pubkey; 12] = [
+            system_program::ID,
+            vote::ID,
+            compute_budget::ID,
+            bpf_loader_deprecated::ID,
+            bpf_loader::ID,
+            bpf_loader_upgradeable::ID,
+            loader_v4::ID,
+            ed25519_program::ID,
+            secp256k1_program::ID,
+            secp256r1_program::ID,
+            zk_token_proof_program::ID,
+            zk_elgamal_proof_program::ID,
+        ];
+        let migrating = [
+            (stake::ID, feature_set.migrate_stake_program_to_core_bpf),
+            (config::ID, feature_set.migrate_config_program_to_core_bpf),
+            (
+                address_lookup_table::ID,
+                feature_set.migrate_address_lookup_table_program_to_core_bpf,
+            ),
+        ];
+
+        if let Some(&(_, migrated)) = migrating.iter().find(|(id, _)| id == program_id) {
+            ProgramKind::MigratingBuiltin { migrated }
+        } else if BUILTINS.contains(program_id) {
+            ProgramKind::Builtin
+        } else {
+            ProgramKind::NotBuiltin
+        }
+    }
+
+    fn is_builtin(self) -> bool {
+        match self {
+            ProgramKind::NotBuiltin => false,
+            ProgramKind::Builtin => true,
+            ProgramKind::MigratingBuiltin { migrated } => !migrated,
+        }
+    }
+}
+
+/// The compute unit limit the runtime applies to instructions invoking
+/// `program_ids` when the message doesn't request one.
+pub fn default_compute_unit_limit<'a>(
+    program_ids: impl IntoIterator<Item = &'a Pubkey>,
+    feature_set: &FeatureSet,
+) -> u32 {
+    let limit = program_ids
+        .into_iter()
+        .map(|program_id| {
+            if feature_set.reserve_minimal_cus_for_builtin_instructions {
+                if ProgramKind::classify(program_id, feature_set).is_builtin() {
+                    MAX_BUILTIN_ALLOCATION_COMPUTE_UNIT_LIMIT
+                } else {
+                    DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT
+                }
+            } else if compute_budget::check_id(program_id) {
+                0
+            } else {
+                DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT
+            }
+        })
+        .fold(0u32, u32::saturating_add);
+    limit.min(MAX_COMPUTE_UNIT_LIMIT)
+}
+
+impl ComputeBudgetRequest {
+    /// The requested compute unit limit, or the runtime default for
+    /// instructions invoking `program_ids`, capped at
+    /// [`MAX_COMPUTE_UNIT_LIMIT`].
+    pub fn resolve_compute_unit_limit<'a>(
+        &self,
+        program_ids: impl IntoIterator<Item = &'a Pubkey>,
+        feature_set: &FeatureSet,
+    ) -> u32 {
+        self.compute_unit_limit
+            .map(|limit| limit.min(MAX_COMPUTE_UNIT_LIMIT))
+            .unwrap_or_else(|| default_compute_unit_limit(program_ids, feature_set))
+    }
+}
+
+#[test]
+fn test_default_compute_unit_limit() {
+    let program = Pubkey::new_unique();
+    let program_ids = [
+        compute_budget::ID,
+        system_program::ID,
+        stake::ID,
+        vote::ID,
+        program,
+    ];
+    let before = FeatureSet::default();
+    let reserved = FeatureSet {
+        reserve_minimal_cus_for_builtin_instructions: true,
+        ..FeatureSet::default()
+    };
+    let migrated = FeatureSet::all_enabled();
+
+    // compute budget instructions reserve nothing until builtins reserve
+    // their own minimum, and stake counts as a builtin until it's migrated
+    assert_eq!(
+        default_compute_unit_limit(&program_ids, &before),
+        4 * DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT
+    );
+    assert_eq!(
+        default_compute_unit_limit(&program_ids, &reserved),
+        4 * MAX_BUILTIN_ALLOCATION_COMPUTE_UNIT_LIMIT + DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT
+    );
+    assert_eq!(
+        default_compute_unit_limit(&program_ids, &migrated),
+        3 * MAX_BUILTIN_ALLOCATION_COMPUTE_UNIT_LIMIT + 2 * DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT
+    );
+    assert_eq!(
+        ProgramKind::classify(&stake::ID, &migrated),
+        ProgramKind::MigratingBuiltin { migrated: true }
+    );
+
+    // the default is capped, and a requested limit takes precedence over it
+    assert_eq!(
+        default_compute_unit_limit(&[program; 8], &migrated),
+        MAX_COMPUTE_UNIT_LIMIT
+    );
+    let requested = ComputeBudgetRequest {
+        compute_unit_limit: Some(u32::MAX),
+        ..ComputeBudgetRequest::default()
+    };
+    assert_eq!(
+        requested.resolve_compute_unit_limit(&program_ids, &before),
+        MAX_COMPUTE_UNIT_LIMIT
+    );
+    assert_eq!(
+        ComputeBudgetRequest::default().resolve_compute_unit_limit(&[], &migrated),
+        0
+    );
+}
@@ -0,0 +1,262 @@
+//! Extract the requested compute budget from any message version.
+//!
+//! Legacy and v0 messages carry their budget in compute budget program
+//! instructions. The candidate versions carry some or all of it in the header,
+//! and may still fall back to instructions for fields the header can't express.
+
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_instruction::error::InstructionError;
+use solana_message::compiled_instruction::CompiledInstruction;
+use solana_pubkey::Pubkey;
+use solana_transaction_error::TransactionError;
+
+#[cfg(feature = "v3b")]
+use crate::v3b;
+use crate::{v1, v2, v3, v3::ComputeBudgetFlags, v4, v5};
+
+/// Compute budget values requested by a message, however they were encoded.
+/// `None` means the message leaves the value to the runtime default.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeBudgetRequest {
+    pub compute_unit_limit: Option<u32>,
+    pub compute_unit_price: Option<u64>,
+    pub loaded_accounts_data_limit: Option<u32>,
+    pub requested_heap_bytes: Option<u32>,
+    /// Only the v3 header family can carry a fee cap, there is no compute
+    /// budget instruction for it.
+    pub max_fee_lamports: Option<u64>,
+}
+
+/// Decode compute budget program instruction data. Trailing bytes are ignored,
+/// matching how the runtime deserializes these instructions.
+pub fn decode_compute_budget_instruction(data: &[u8]) -> Option<ComputeBudgetInstruction> {
+    let (&tag, rest) = data.split_first()?;
+    let read_u32 = || Some(u32::from_le_bytes(rest.get(..4)?.try_into().ok()?));
+    match tag {
+        0 => Some(ComputeBudgetInstruction::Unused),
+        1 => read_u32().map(ComputeBudgetInstruction::RequestHeapFrame),
+        2 => read_u32().map(ComputeBudgetInstruction::SetComputeUnitLimit),
+        3 => Some(ComputeBudgetInstruction::SetComputeUnitPrice(
+            u64::from_le_bytes(rest.get(..8)?.try_into().ok()?),
+        )),
+        4 => read_u32().map(ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit),
+        _ => None,
+    }
+}
+
+impl ComputeBudgetRequest {
+    /// Fill in the fields not already set from the compute budget program
+    /// instructions in `instructions`. Setting a field twice, either through
+    /// two instructions or an instruction and the header, is a
+    /// `DuplicateInstruction` error.
+    pub fn process_instructions(
+        mut self,
+        account_keys: &[Pubkey],
+        instructions: &[CompiledInstruction],
+    ) -> Result<ComputeBudgetRequest, TransactionError> {
+        for (index, instruction) in instructions.iter().enumerate() {
+            let is_compute_budget = account_keys
+                .get(usize::from(instruction.program_id_index))
+                .is_some_and(solana_compute_budget_interface::check_id);
+            if !is_compute_budget {
+                continue;
+            }
+
+            let index = index as u8;
+            let invalid = || {
+                TransactionError::InstructionError(index, InstructionError::InvalidInstructionData)
+            };
+            let duplicate = TransactionError::DuplicateInstruction(index);
+            match decode_compute_budget_instruction(&instruction.data).ok_or_else(invalid)? {
+                // the runtime rejects the unused tag like any other bad data
+                ComputeBudgetInstruction::Unused => return Err(invalid()),
+                ComputeBudgetInstruction::RequestHeapFrame(bytes) => {
+                    set_once(&mut self.requested_heap_bytes, bytes, duplicate)?
+                }
+                ComputeBudgetInstruction::SetComputeUnitLimit(units) => {
+                    set_once(&mut self.compute_unit_limit, units, duplicate)?
+                }
+                ComputeBudgetInstruction::SetComputeUnitPrice(micro_lamports) => {
+                    set_once(&mut self.compute_unit_price, micro_lamports, duplicate)?
+                }
+                ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit(bytes) => {
+                    set_once(&mut self.loaded_accounts_data_limit, bytes, duplicate)?
+                }
+            }
+        }
+        Ok(self)
+    }
+}
+
+fn set_once<T>(
+    field: &mut Option<T>,
+    value: T,
+    err: TransactionError,
+) -> Result<(), TransactionError> {
+    if field.is_some() {
+        return Err(err);
+    }
+    *field = Some(value);
+    Ok(())
+}
+
+/// Zero in the fixed-width v1/v2 header fields means "not requested".
+fn non_zero<T: Default + PartialEq>(value: T) -> Option<T> {
+    (value != T::default()).then_some(value)
+}
+
+impl v1::Message {
+    /// The compute budget requested by the header and any remaining compute
+    /// budget program instructions.
+    pub fn compute_budget_request(&self) -> Result<ComputeBudgetRequest, TransactionError> {
+        ComputeBudgetRequest {
+            compute_unit_limit: non_zero(self.header.compute_unit_limit),
+            compute_unit_price: non_zero(self.header.compute_unit_price),
+            ..ComputeBudgetRequest::default()
+        }
+        .process_instructions(&self.account_keys, &self.instructions)
+    }
+}
+
+impl v2::Message {
+    /// The compute budget requested by the header and any remaining compute
+    /// budget program instructions.
+    pub fn compute_budget_request(&self) -> Result<ComputeBudgetRequest, TransactionError> {
+        ComputeBudgetRequest {
+            compute_unit_limit: non_zero(self.header.compute_unit_limit),
+            compute_unit_price: non_zero(self.header.compute_unit_price),
+            loaded_accounts_data_limit: non_zero(self.header.loaded_accounts_data_limit),
+            requested_heap_bytes: non_zero(self.header.requested_heap_bytes),
+            ..ComputeBudgetRequest::default()
+        }
+        .process_instructions(&self.account_keys, &self.instructions)
+    }
+}
+
+/// The accessors shared by the v3, v4 and v5 compute budget headers, which
+/// only differ in how they encode the fields.
+pub trait ComputeBudgetHeaderFields {
+    fn flags(&self) -> ComputeBudgetFlags;
+    /// Number of bytes the header occupies on the wire.
+    fn serialized_size(&self) -> usize;
+    /// The values the header requests, without any compute budget program
+    /// instructions.
+    fn compute_budget_request(&self) -> ComputeBudgetRequest;
+}
+
+macro_rules! impl_compute_budget_header_fields {
+    ($($version:ident),*) => {$(
+        impl ComputeBudgetHeaderFields for $version::ComputeBudgetHeader {
+            fn flags(&self) -> ComputeBudgetFlags {
+                $version::ComputeBudgetHeader::flags(self)
+            }
+
+            fn serialized_size(&self) -> usize {
+                $version::ComputeBudgetHeader::serialized_size(self)
+            }
+
+            fn compute_budget_request(&self) -> ComputeBudgetRequest {
+                ComputeBudgetRequest {
+                    compute_unit_limit: self.compute_unit_limit(),
+                    compute_unit_price: self.compute_unit_price(),
+                    loaded_accounts_data_limit: self.loaded_accounts_data_limit(),
+                    requested_heap_bytes: self.requested_heap_bytes_limit(),
+                    max_fee_lamports: self.max_fee_lamports(),
+                }
+            }
+        }
+    )*};
+}
+
+impl_compute_budget_header_fields!(v3, v4, v5);
+
+/// `compute_budget_request` for the versions with a [`ComputeBudgetHeaderFields`]
+/// header.
+macro_rules! impl_compute_budget_request {
+    ($($version:ident),*) => {$(
+        impl $version::Message {
+            /// The compute budget requested by the header and any remaining
+            /// compute budget program instructions.
+            pub fn compute_budget_request(&self) -> Result<ComputeBudgetRequest, TransactionError> {
+                ComputeBudgetHeaderFields::compute_budget_request(&self.compute_budget_header)
+                    .process_instructions(&self.account_keys, &self.instructions)
+            }
+        }
+    )*};
+}
+
+impl_compute_budget_request!(v3, v4, v5);
+#[cfg(feature = "v3b")]
+impl_compute_budget_request!(v3b);
+
+#[test]
+fn test_compute_budget_request() {
+    use solana_hash::Hash;
+    use solana_message::{MessageHeader, v0};
+
+    let payer = Pubkey::new_unique();
+    let instructions = [
+        ComputeBudgetInstruction::set_compute_unit_limit(12),
+        ComputeBudgetInstruction::set_compute_unit_price(34),
+        ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(56),
+        ComputeBudgetInstruction::request_heap_frame(78),
+    ];
+    let v0_message =
+        v0::Message::try_compile(&payer, &instructions, &[], Hash::new_unique()).unwrap();
+    let expected = ComputeBudgetRequest {
+        compute_unit_limit: Some(12),
+        compute_unit_price: Some(34),
+        loaded_accounts_data_limit: Some(56),
+        requested_heap_bytes: Some(78),
+        ..ComputeBudgetRequest::default()
+    };
+    assert_eq!(
+        ComputeBudgetRequest::default()
+            .process_instructions(&v0_message.account_keys, &v0_message.instructions),
+        Ok(expected)
+    );
+
+    let v3_message = v3::Message {
+        compute_budget_header: v3::ComputeBudgetHeader::new(Some(12), Some(34), None, None),
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 1,
+        },
+        account_keys: v0_message.account_keys.clone(),
+        recent_blockhash: v0_message.recent_blockhash,
+        instructions: v0_message.instructions[2..].to_vec(),
+        address_table_lookups: vec![],
+    };
+    assert_eq!(v3_message.compute_budget_request(), Ok(expected));
+
+    // the header already sets the limit
+    let mut duplicate = v3_message.clone();
+    duplicate.instructions = v0_message.instructions[..1].to_vec();
+    assert_eq!(
+        duplicate.compute_budget_request(),
+        Err(TransactionError::DuplicateInstruction(0))
+    );
+
+    let mut invalid = v3_message;
+    invalid.instructions[1].data.truncate(3);
+    assert_eq!(
+        invalid.compute_budget_request(),
+        Err(TransactionError::InstructionError(
+            1,
+            InstructionError::InvalidInstructionData
+        ))
+    );
+    invalid.instructions[1].data = vec![0];
+    assert_eq!(
+        decode_compute_budget_instruction(&invalid.instructions[1].data),
+        Some(ComputeBudgetInstruction::Unused)
+    );
+    assert_eq!(
+        invalid.compute_budget_request(),
+        Err(TransactionError::InstructionError(
+            1,
+            InstructionError::InvalidInstructionData
+        ))
+    );
+}
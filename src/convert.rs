@@ -0,0 +1,620 @@
+//! Conversions between v0 and the candidate message versions.
+//!
+//! Converting from v0 folds compute budget program instructions into the
+//! target's header wherever the target can express them. Anything it can't
+//! express (v1 has no loaded accounts or heap fields, v1/v2 can't encode an
+//! explicit zero, malformed or duplicate instructions) is left in place so the
+//! converted message requests exactly the same budget. Once nothing invokes or
+//! references the compute budget program its key is dropped.
+//!
+//! Converting to v0 does the reverse: header values become compute budget
+//! instructions at the front of the message, and the compute budget program is
+//! appended to the static keys if it isn't already there. A fee cap has no
+//! instruction to become, so messages carrying one don't convert.
+
+use core::fmt;
+
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_message::{MessageHeader, compiled_instruction::CompiledInstruction, v0};
+
+#[cfg(feature = "v3b")]
+use crate::v3b;
+use crate::{
+    compute_budget::{ComputeBudgetHeaderFields, decode_compute_budget_instruction},
+    v1, v2, v3, v4, v5,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConvertError {
+    /// Adding the compute budget program would take the message past the 256
+    /// account keys that `u8` indexes can address.
+    TooManyAccountKeys,
+    /// Legacy messages can't carry address table lookups.
+    AddressTableLookupsUnsupported,
+    /// The compute budget program has no instruction for a fee cap.
+    MaxFeeUnsupported,
+}
+
+impl std::error::Error for ConvertError {}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConvertError::TooManyAccountKeys => f.write_str("too many account keys"),
+            ConvertError::AddressTableLookupsUnsupported => {
+                f.write_str("legacy messages do not support address table lookups")
+            }
+            ConvertError::MaxFeeUnsupported => f.write_str("v0 messages do not support a max fee"),
+        }
+    }
+}
+
+/// Indexes of the instructions that invoke the compute budget program.
+pub fn compute_budget_instruction_indexes(
+    account_keys: &[solana_pubkey::Pubkey],
+    instructions: &[CompiledInstruction],
+) -> Vec<usize> {
+    instructions
+        .iter()
+        .enumerate()
+        .filter(|(_, ix)| {
+            account_keys
+                .get(usize::from(ix.program_id_index))
+                .is_some_and(solana_compute_budget_interface::check_id)
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Remove every compute budget instruction `fold` accepts, then drop the
+/// compute budget program key if nothing references it anymore.
+fn fold_compute_budget(
+    message: &v0::Message,
+    mut fold: impl FnMut(ComputeBudgetInstruction) -> bool,
+) -> v0::Message {
+    let compute_budget_indexes =
+        compute_budget_instruction_indexes(&message.account_keys, &message.instructions);
+    let mut folded = message.clone();
+    let mut index = 0;
+    folded.instructions.retain(|ix| {
+        let is_compute_budget = compute_budget_indexes.contains(&index);
+        index += 1;
+        !(is_compute_budget && decode_compute_budget_instruction(&ix.data).is_some_and(&mut fold))
+    });
+    remove_unused_compute_budget_key(&mut folded);
+    folded
+}
+
+fn remove_unused_compute_budget_key(message: &mut v0::Message) {
+    let Some(key_index) = message
+        .account_keys
+        .iter()
+        .position(solana_compute_budget_interface::check_id)
+    else {
+        return;
+    };
+
+    // only a readonly, unsigned key can be dropped without touching the
+    // other header counts
+    let num_static_account_keys = message.account_keys.len();
+    let first_readonly_unsigned = num_static_account_keys
+        .saturating_sub(usize::from(message.header.num_readonly_unsigned_accounts));
+    if key_index < first_readonly_unsigned
+        || key_index < usize::from(message.header.num_required_signatures)
+    {
+        return;
+    }
+
+    let key_index = key_index as u8;
+    let referenced = message
+        .instructions
+        .iter()
+        .any(|ix| ix.program_id_index == key_index || ix.accounts.contains(&key_index));
+    if referenced {
+        return;
+    }
+
+    message.account_keys.remove(usize::from(key_index));
+    message.header.num_readonly_unsigned_accounts -= 1;
+    for ix in &mut message.instructions {
+        if ix.program_id_index > key_index {
+            ix.program_id_index -= 1;
+        }
+        for account in &mut ix.accounts {
+            if *account > key_index {
+                *account -= 1;
+            }
+        }
+    }
+}
+
+/// Rebuild a v0 message with `budget_instructions` (instruction data for the
+/// compute budget program) in front of the existing instructions.
+fn unfold_compute_budget(
+    header: MessageHeader,
+    message: v0::Message,
+    budget_instructions: Vec<Vec<u8>>,
+) -> Result<v0::Message, ConvertError> {
+    let mut message = v0::Message { header, ..message };
+    if budget_instructions.is_empty() {
+        return Ok(message);
+    }
+
+    let key_index = match message
+        .account_keys
+        .iter()
+        .position(solana_compute_budget_interface::check_id)
+    {
+        Some(key_index) => key_index as u8,
+        None => {
+            let num_static_account_keys = message.account_keys.len();
+            let num_dynamic_account_keys: usize = message
+                .address_table_lookups
+                .iter()
+                .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+                .sum();
+            if num_static_account_keys + num_dynamic_account_keys >= 256 {
+                return Err(ConvertError::TooManyAccountKeys);
+            }
+            message.header.num_readonly_unsigned_accounts = message
+                .header
+                .num_readonly_unsigned_accounts
+                .checked_add(1)
+                .ok_or(ConvertError::TooManyAccountKeys)?;
+
+            // loaded addresses are indexed after the static keys, so they
+            // shift by one
+            let key_index = num_static_account_keys as u8;
+            for ix in &mut message.instructions {
+                for account in &mut ix.accounts {
+                    if *account >= key_index {
+                        *account += 1;
+                    }
+                }
+            }
+            message
+                .account_keys
+                .push(solana_compute_budget_interface::id());
+            key_index
+        }
+    };
+
+    let instructions = budget_instructions
+        .into_iter()
+        .map(|data| CompiledInstruction::new_from_raw_parts(key_index, data, vec![]))
+        .chain(message.instructions)
+        .collect();
+    Ok(v0::Message {
+        instructions,
+        ..message
+    })
+}
+
+fn budget_instructions(
+    compute_unit_limit: Option<u32>,
+    compute_unit_price: Option<u64>,
+    loaded_accounts_data_limit: Option<u32>,
+    requested_heap_bytes: Option<u32>,
+) -> Vec<Vec<u8>> {
+    [
+        compute_unit_limit.map(ComputeBudgetInstruction::set_compute_unit_limit),
+        compute_unit_price.map(ComputeBudgetInstruction::set_compute_unit_price),
+        loaded_accounts_data_limit
+            .map(ComputeBudgetInstruction::set_loaded_accounts_data_size_limit),
+        requested_heap_bytes.map(ComputeBudgetInstruction::request_heap_frame),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|ix| ix.data)
+    .collect()
+}
+
+/// Zero in the fixed-width v1/v2 header fields means "not requested", so an
+/// explicit zero can't be folded into them.
+fn fold_non_zero<T: Default + PartialEq>(field: &mut T, value: T) -> bool {
+    if *field != T::default() || value == T::default() {
+        return false;
+    }
+    *field = value;
+    true
+}
+
+fn fold_option<T>(field: &mut Option<T>, value: T) -> bool {
+    if field.is_some() {
+        return false;
+    }
+    *field = Some(value);
+    true
+}
+
+impl v1::Message {
+    /// Fold the compute unit limit and price instructions of a v0 message
+    /// into a v1 header.
+    pub fn from_v0(message: &v0::Message) -> v1::Message {
+        let mut compute_unit_limit = 0;
+        let mut compute_unit_price = 0;
+        let folded = fold_compute_budget(message, |ix| match ix {
+            ComputeBudgetInstruction::SetComputeUnitLimit(units) => {
+                fold_non_zero(&mut compute_unit_limit, units)
+            }
+            ComputeBudgetInstruction::SetComputeUnitPrice(micro_lamports) => {
+                fold_non_zero(&mut compute_unit_price, micro_lamports)
+            }
+            _ => false,
+        });
+
+        v1::Message {
+            header: v1::MessageHeader {
+                compute_unit_price,
+                compute_unit_limit,
+                num_required_signatures: folded.header.num_required_signatures,
+                num_readonly_signed_accounts: folded.header.num_readonly_signed_accounts,
+                num_readonly_unsigned_accounts: folded.header.num_readonly_unsigned_accounts,
+            },
+            account_keys: folded.account_keys,
+            recent_blockhash: folded.recent_blockhash,
+            instructions: folded.instructions,
+            address_table_lookups: folded.address_table_lookups,
+        }
+    }
+
+    /// Move the header's compute budget fields back into instructions.
+    pub fn to_v0(&self) -> Result<v0::Message, ConvertError> {
+        unfold_compute_budget(
+            self.header.as_message_header(),
+            v0::Message {
+                header: MessageHeader::default(),
+                account_keys: self.account_keys.clone(),
+                recent_blockhash: self.recent_blockhash,
+                instructions: self.instructions.clone(),
+                address_table_lookups: self.address_table_lookups.clone(),
+            },
+            budget_instructions(
+                (self.header.compute_unit_limit != 0).then_some(self.header.compute_unit_limit),
+                (self.header.compute_unit_price != 0).then_some(self.header.compute_unit_price),
+                None,
+                None,
+            ),
+        )
+    }
+}
+
+impl v2::Message {
+    /// Fold all compute budget instructions of a v0 message into a v2 header.
+    pub fn from_v0(message: &v0::Message) -> v2::Message {
+        let mut compute_unit_limit = 0;
+        let mut compute_unit_price = 0;
+        let mut loaded_accounts_data_limit = 0;
+        let mut requested_heap_bytes = 0;
+        let folded = fold_compute_budget(message, |ix| match ix {
+            ComputeBudgetInstruction::SetComputeUnitLimit(units) => {
+                fold_non_zero(&mut compute_unit_limit, units)
+            }
+            ComputeBudgetInstruction::SetComputeUnitPrice(micro_lamports) => {
+                fold_non_zero(&mut compute_unit_price, micro_lamports)
+            }
+            ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit(bytes) => {
+                fold_non_zero(&mut loaded_accounts_data_limit, bytes)
+            }
+            ComputeBudgetInstruction::RequestHeapFrame(bytes) => {
+                fold_non_zero(&mut requested_heap_bytes, bytes)
+            }
+            ComputeBudgetInstruction::Unused => false,
+        });
+
+        v2::Message {
+            header: v2::MessageHeader {
+                compute_unit_price,
+                compute_unit_limit,
+                loaded_accounts_data_limit,
+                requested_heap_bytes,
+                num_required_signatures: folded.header.num_required_signatures,
+                num_readonly_signed_accounts: folded.header.num_readonly_signed_accounts,
+                num_readonly_unsigned_accounts: folded.header.num_readonly_unsigned_accounts,
+            },
+            account_keys: folded.account_keys,
+            recent_blockhash: folded.recent_blockhash,
+            instructions: folded.instructions,
+            address_table_lookups: folded.address_table_lookups,
+        }
+    }
+
+    /// Move the header's compute budget fields back into instructions.
+    pub fn to_v0(&self) -> Result<v0::Message, ConvertError> {
+        let non_zero = |value: u32| (value != 0).then_some(value);
+        unfold_compute_budget(
+            self.header.as_message_header(),
+            v0::Message {
+                header: MessageHeader::default(),
+                account_keys: self.account_keys.clone(),
+                recent_blockhash: self.recent_blockhash,
+                instructions: self.instructions.clone(),
+                address_table_lookups: self.address_table_lookups.clone(),
+            },
+            budget_instructions(
+                non_zero(self.header.compute_unit_limit),
+                (self.header.compute_unit_price != 0).then_some(self.header.compute_unit_price),
+                non_zero(self.header.loaded_accounts_data_limit),
+                non_zero(self.header.requested_heap_bytes),
+            ),
+        )
+    }
+}
+
+/// Fold the compute budget instructions of a v0 message into a compute budget
+/// header built by `new`. `price` encodes a compute unit price the way the
+/// header does; a price it can't encode stays an instruction.
+fn fold_compute_budget_header<P, H>(
+    message: &v0::Message,
+    price: impl Fn(u64) -> Option<P>,
+    new: impl FnOnce(Option<u32>, Option<P>, Option<u32>, Option<u32>) -> H,
+) -> (H, v0::Message) {
+    let mut compute_unit_limit = None;
+    let mut compute_unit_price = None;
+    let mut loaded_accounts_data_limit = None;
+    let mut requested_heap_bytes = None;
+    let folded = fold_compute_budget(message, |ix| match ix {
+        ComputeBudgetInstruction::SetComputeUnitLimit(units) => {
+            fold_option(&mut compute_unit_limit, units)
+        }
+        ComputeBudgetInstruction::SetComputeUnitPrice(micro_lamports) => {
+            price(micro_lamports).is_some_and(|price| fold_option(&mut compute_unit_price, price))
+        }
+        ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit(bytes) => {
+            fold_option(&mut loaded_accounts_data_limit, bytes)
+        }
+        ComputeBudgetInstruction::RequestHeapFrame(bytes) => {
+            fold_option(&mut requested_heap_bytes, bytes)
+        }
+        ComputeBudgetInstruction::Unused => false,
+    });
+
+    let compute_budget_header = new(
+        compute_unit_limit,
+        compute_unit_price,
+        loaded_accounts_data_limit,
+        requested_heap_bytes,
+    );
+    (compute_budget_header, folded)
+}
+
+/// Rebuild a v0 message with the values of `compute_budget_header` as
+/// instructions.
+fn unfold_compute_budget_header(
+    compute_budget_header: &impl ComputeBudgetHeaderFields,
+    header: MessageHeader,
+    message: v0::Message,
+) -> Result<v0::Message, ConvertError> {
+    let request = compute_budget_header.compute_budget_request();
+    if request.max_fee_lamports.is_some() {
+        return Err(ConvertError::MaxFeeUnsupported);
+    }
+    unfold_compute_budget(
+        header,
+        message,
+        budget_instructions(
+            request.compute_unit_limit,
+            request.compute_unit_price,
+            request.loaded_accounts_data_limit,
+            request.requested_heap_bytes,
+        ),
+    )
+}
+
+/// `from_v0` and `to_v0` for the versions with a [`ComputeBudgetHeaderFields`]
+/// header, given how each encodes the compute unit price.
+macro_rules! impl_compute_budget_header_conversions {
+    ($($version:ident => $price:expr),*) => {$(
+        impl $version::Message {
+            /// Fold the compute budget instructions of a v0 message into the
+            /// compute budget header. A price the header can't express stays
+            /// an instruction.
+            pub fn from_v0(message: &v0::Message) -> $version::Message {
+                let (compute_budget_header, folded) = fold_compute_budget_header(
+                    message,
+                    $price,
+                    $version::ComputeBudgetHeader::new,
+                );
+                $version::Message {
+                    compute_budget_header,
+                    header: folded.header,
+                    account_keys: folded.account_keys,
+                    recent_blockhash: folded.recent_blockhash,
+                    instructions: folded.instructions,
+                    address_table_lookups: folded.address_table_lookups,
+                }
+            }
+
+            /// Move the compute budget header back into instructions.
+            pub fn to_v0(&self) -> Result<v0::Message, ConvertError> {
+                unfold_compute_budget_header(
+                    &self.compute_budget_header,
+                    self.header,
+                    v0::Message {
+                        header: MessageHeader::default(),
+                        account_keys: self.account_keys.clone(),
+                        recent_blockhash: self.recent_blockhash,
+                        instructions: self.instructions.clone(),
+                        address_table_lookups: self.address_table_lookups.clone(),
+                    },
+                )
+            }
+        }
+    )*};
+}
+
+impl_compute_budget_header_conversions!(
+    v3 => Some,
+    v4 => Some,
+    // only prices whose mantissa fits in a u32 can be scaled
+    v5 => |micro_lamports| v5::ScaledPrice::try_from(micro_lamports).ok()
+);
+#[cfg(feature = "v3b")]
+impl_compute_budget_header_conversions!(v3b => Some);
+
+#[cfg(feature = "serde")]
+mod versioned {
+    use solana_message::{legacy, v0};
+
+    use super::ConvertError;
+    #[cfg(feature = "v3b")]
+    use crate::v3b;
+    use crate::{
+        v1, v2, v3, v4, v5,
+        versioned::{MessageVersion, VersionedMessage},
+    };
+
+    impl VersionedMessage {
+        /// The equivalent v0 message, with every compute budget value carried
+        /// by compute budget instructions.
+        pub fn to_v0(&self) -> Result<v0::Message, ConvertError> {
+            match self {
+                Self::Legacy(message) => Ok(v0::Message {
+                    header: message.header,
+                    account_keys: message.account_keys.clone(),
+                    recent_blockhash: message.recent_blockhash,
+                    instructions: message.instructions.clone(),
+                    address_table_lookups: vec![],
+                }),
+                Self::V0(message) => Ok(message.clone()),
+                Self::V1(message) => message.to_v0(),
+                Self::V2(message) => message.to_v0(),
+                Self::V3(message) => message.to_v0(),
+                Self::V4(message) => message.to_v0(),
+                Self::V5(message) => message.to_v0(),
+                #[cfg(feature = "v3b")]
+                Self::V3b(message) => message.to_v0(),
+            }
+        }
+
+        /// Convert to another message version, going through v0.
+        pub fn convert(&self, version: MessageVersion) -> Result<VersionedMessage, ConvertError> {
+            if self.version() == version {
+                return Ok(self.clone());
+            }
+            let message = self.to_v0()?;
+            Ok(match version {
+                MessageVersion::Legacy => {
+                    if !message.address_table_lookups.is_empty() {
+                        return Err(ConvertError::AddressTableLookupsUnsupported);
+                    }
+                    VersionedMessage::Legacy(legacy::Message {
+                        header: message.header,
+                        account_keys: message.account_keys,
+                        recent_blockhash: message.recent_blockhash,
+                        instructions: message.instructions,
+                    })
+                }
+                MessageVersion::V0 => VersionedMessage::V0(message),
+                MessageVersion::V1 => VersionedMessage::V1(v1::Message::from_v0(&message)),
+                MessageVersion::V2 => VersionedMessage::V2(v2::Message::from_v0(&message)),
+                MessageVersion::V3 => VersionedMessage::V3(v3::Message::from_v0(&message)),
+                MessageVersion::V4 => VersionedMessage::V4(v4::Message::from_v0(&message)),
+                MessageVersion::V5 => VersionedMessage::V5(v5::Message::from_v0(&message)),
+                #[cfg(feature = "v3b")]
+                MessageVersion::V3b => VersionedMessage::V3b(v3b::Message::from_v0(&message)),
+            })
+        }
+    }
+}
+
+#[test]
+fn test_convert_v0_full() {
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::AddressLookupTableAccount;
+    use solana_pubkey::Pubkey;
+
+    let payer = Pubkey::new_unique();
+    let lookup_table = AddressLookupTableAccount {
+        key: Pubkey::new_unique(),
+        addresses: vec![Pubkey::new_unique()],
+    };
+    let instructions = [
+        ComputeBudgetInstruction::set_compute_unit_limit(12),
+        ComputeBudgetInstruction::set_compute_unit_price(34),
+        ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(56),
+        ComputeBudgetInstruction::request_heap_frame(78),
+        Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[1, 2, 3],
+            vec![AccountMeta::new(lookup_table.addresses[0], false)],
+        ),
+    ];
+    let message =
+        v0::Message::try_compile(&payer, &instructions, &[lookup_table], Hash::new_unique())
+            .unwrap();
+    let request = crate::compute_budget::ComputeBudgetRequest::default()
+        .process_instructions(&message.account_keys, &message.instructions)
+        .unwrap();
+
+    // v2, v3 and v4 fold everything and drop the compute budget program
+    let v3 = v3::Message::from_v0(&message);
+    assert_eq!(v3.instructions.len(), 1);
+    assert!(
+        !v3.account_keys
+            .contains(&solana_compute_budget_interface::id())
+    );
+    assert_eq!(v3.compute_budget_request(), Ok(request));
+    v3.sanitize().unwrap();
+    assert_eq!(v3::Message::from_v0(&v3.to_v0().unwrap()), v3);
+
+    let v4 = v4::Message::from_v0(&message);
+    assert_eq!(v4.account_keys, v3.account_keys);
+    assert_eq!(v4.compute_budget_request(), Ok(request));
+    v4.sanitize().unwrap();
+    assert_eq!(v4::Message::from_v0(&v4.to_v0().unwrap()), v4);
+
+    let v5 = v5::Message::from_v0(&message);
+    assert_eq!(v5.account_keys, v3.account_keys);
+    assert_eq!(v5.compute_budget_request(), Ok(request));
+    v5.sanitize().unwrap();
+    assert_eq!(v5::Message::from_v0(&v5.to_v0().unwrap()), v5);
+
+    // v3b folds exactly what v3 does
+    #[cfg(feature = "v3b")]
+    {
+        let v3b = v3b::Message::from_v0(&message);
+        assert_eq!(v3::Message::from(v3b.clone()), v3);
+        assert_eq!(v3b.compute_budget_request(), Ok(request));
+        assert_eq!(v3b.to_v0(), v3.to_v0());
+    }
+
+    // there's no instruction to carry a fee cap back to v0
+    let mut capped = v3.clone();
+    capped.compute_budget_header = capped
+        .compute_budget_header
+        .with_max_fee_lamports(Some(1_000));
+    assert_eq!(capped.to_v0(), Err(ConvertError::MaxFeeUnsupported));
+
+    // a price v5 can't scale exactly keeps its instruction
+    let mut lossy = message.clone();
+    lossy.instructions[1].data = ComputeBudgetInstruction::set_compute_unit_price(u64::MAX).data;
+    let v5 = v5::Message::from_v0(&lossy);
+    assert_eq!(v5.compute_budget_header.compute_unit_price(), None);
+    assert_eq!(
+        compute_budget_instruction_indexes(&v5.account_keys, &v5.instructions),
+        vec![0]
+    );
+    assert_eq!(
+        v5.compute_budget_request().unwrap().compute_unit_price,
+        Some(u64::MAX)
+    );
+    assert_eq!(v5::Message::from_v0(&v5.to_v0().unwrap()), v5);
+
+    let v2 = v2::Message::from_v0(&message);
+    assert_eq!(v2.instructions.len(), 1);
+    assert_eq!(v2.compute_budget_request(), Ok(request));
+    v2.sanitize().unwrap();
+    assert_eq!(v2::Message::from_v0(&v2.to_v0().unwrap()), v2);
+
+    // v1 keeps the loaded accounts and heap instructions
+    let v1 = v1::Message::from_v0(&message);
+    assert_eq!(
+        compute_budget_instruction_indexes(&v1.account_keys, &v1.instructions),
+        vec![0, 1]
+    );
+    assert_eq!(v1.compute_budget_request(), Ok(request));
+    v1.sanitize().unwrap();
+    assert_eq!(v1::Message::from_v0(&v1.to_v0().unwrap()), v1);
+}
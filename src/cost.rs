@@ -0,0 +1,252 @@
+//! Block cost of a transaction, as the runtime's cost model estimates it.
+//!
+//! The constants mirror `solana-cost-model`'s. The only difference between
+//! versions is where the compute budget comes from: the header for the
+//! candidate versions, compute budget instructions otherwise. Both end up in
+//! [`SanitizedMessageInfo::compute_budget_request`], so one calculation
+//! covers every version.
+
+use solana_message::compiled_instruction::CompiledInstruction;
+use solana_pubkey::Pubkey;
+use solana_sdk_ids::{ed25519_program, secp256k1_program, secp256r1_program};
+
+use crate::{builtins::FeatureSet, sanitized::SanitizedMessageInfo};
+
+/// Cost units per microsecond of execution.
+pub const COMPUTE_UNIT_TO_US_RATIO: u64 = 30;
+/// Cost of verifying one transaction signature.
+pub const SIGNATURE_COST: u64 = COMPUTE_UNIT_TO_US_RATIO * 24;
+/// Cost of verifying one secp256k1 precompile signature.
+pub const SECP256K1_VERIFY_COST: u64 = COMPUTE_UNIT_TO_US_RATIO * 223;
+/// Cost of verifying one ed25519 precompile signature. This is the strict
+/// verification cost, which the runtime charges once
+/// `ed25519_precompile_verify_strict` is active.
+pub const ED25519_VERIFY_STRICT_COST: u64 = COMPUTE_UNIT_TO_US_RATIO * 80;
+/// Cost of verifying one secp256r1 precompile signature.
+pub const SECP256R1_VERIFY_COST: u64 = COMPUTE_UNIT_TO_US_RATIO * 160;
+/// Cost of taking one write lock.
+pub const WRITE_LOCK_UNITS: u64 = COMPUTE_UNIT_TO_US_RATIO * 10;
+/// Instruction data bytes per cost unit.
+pub const INSTRUCTION_DATA_BYTES_COST: u64 = 140 / COMPUTE_UNIT_TO_US_RATIO;
+
+/// Loaded accounts data size when no limit is requested.
+pub const MAX_LOADED_ACCOUNTS_DATA_SIZE_BYTES: u32 = 64 * 1024 * 1024;
+/// Loaded accounts data is charged per page of this many bytes.
+pub const ACCOUNT_DATA_COST_PAGE_SIZE: u64 = 32 * 1024;
+/// Cost of each page of loaded accounts data.
+pub const HEAP_COST: u64 = 8;
+
+/// The components of a transaction's block cost.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionCost {
+    /// Transaction signatures and precompile signatures.
+    pub signature_cost: u64,
+    pub write_lock_cost: u64,
+    pub data_bytes_cost: u64,
+    /// The requested compute unit limit, or the default for the instructions
+    /// under the feature set.
+    pub programs_execution_cost: u64,
+    pub loaded_accounts_data_size_cost: u64,
+}
+
+impl TransactionCost {
+    /// Estimate the cost of `message` from its account roles, instructions
+    /// and compute budget.
+    pub fn calculate<T: SanitizedMessageInfo + ?Sized>(
+        message: &T,
+        feature_set: &FeatureSet,
+    ) -> Self {
+        let account_keys = message.account_keys();
+        let program_instructions = || {
+            message.instructions().iter().map(|instruction| {
+                (
+                    &account_keys[usize::from(instruction.program_id_index)],
+                    instruction,
+                )
+            })
+        };
+        let compute_budget_request = message.compute_budget_request();
+
+        let num_write_locks = (0..account_keys.len())
+            .filter(|&key_index| message.is_writable(key_index))
+            .count() as u64;
+        let data_bytes: u64 = message
+            .instructions()
+            .iter()
+            .map(|instruction| instruction.data.len() as u64)
+            .sum();
+        let compute_unit_limit = compute_budget_request.resolve_compute_unit_limit(
+            program_instructions().map(|(program_id, _)| program_id),
+            feature_set,
+        );
+        let loaded_accounts_data_size = compute_budget_request
+            .loaded_accounts_data_limit
+            .unwrap_or(MAX_LOADED_ACCOUNTS_DATA_SIZE_BYTES);
+
+        Self {
+            signature_cost: signature_cost(
+                message.num_required_signatures() as u64,
+                program_instructions(),
+            ),
+            write_lock_cost: WRITE_LOCK_UNITS * num_write_locks,
+            data_bytes_cost: data_bytes / INSTRUCTION_DATA_BYTES_COST,
+            programs_execution_cost: u64::from(compute_unit_limit),
+            loaded_accounts_data_size_cost: u64::from(loaded_accounts_data_size)
+                .div_ceil(ACCOUNT_DATA_COST_PAGE_SIZE)
+                * HEAP_COST,
+        }
+    }
+
+    pub fn sum(&self) -> u64 {
+        self.signature_cost
+            + self.write_lock_cost
+            + self.data_bytes_cost
+            + self.programs_execution_cost
+            + self.loaded_accounts_data_size_cost
+    }
+}
+
+fn signature_cost<'a>(
+    num_transaction_signatures: u64,
+    program_instructions: impl Iterator<Item = (&'a Pubkey, &'a CompiledInstruction)>,
+) -> u64 {
+    program_instructions.fold(
+        num_transaction_signatures * SIGNATURE_COST,
+        |cost, (program_id, instruction)| {
+            let verify_cost = if ed25519_program::check_id(program_id) {
+                ED25519_VERIFY_STRICT_COST
+            } else if secp256k1_program::check_id(program_id) {
+                SECP256K1_VERIFY_COST
+            } else if secp256r1_program::check_id(program_id) {
+                SECP256R1_VERIFY_COST
+            } else {
+                0
+            };
+            cost + num_precompile_signatures(program_id, instruction) * verify_cost
+        },
+    )
+}
+
+/// Precompile instructions verify the number of signatures given by their
+/// first data byte. Any other instruction verifies none.
+pub(crate) fn num_precompile_signatures(
+    program_id: &Pubkey,
+    instruction: &CompiledInstruction,
+) -> u64 {
+    let is_precompile = ed25519_program::check_id(program_id)
+        || secp256k1_program::check_id(program_id)
+        || secp256r1_program::check_id(program_id);
+    if !is_precompile {
+        return 0;
+    }
+    u64::from(instruction.data.first().copied().unwrap_or(0))
+}
+
+#[test]
+fn test_transaction_cost() {
+    use std::collections::{HashMap, HashSet};
+
+    use solana_compute_budget_interface::ComputeBudgetInstruction;
+    use solana_hash::Hash;
+    use solana_message::{MessageHeader, legacy, v0};
+
+    use crate::{
+        builtins::{
+            DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT, MAX_BUILTIN_ALLOCATION_COMPUTE_UNIT_LIMIT,
+        },
+        sanitized::{SanitizedLegacyMessage, SanitizedV0Message, SanitizedV3Message},
+        v3,
+    };
+
+    let payer = Pubkey::new_unique();
+    let writable = Pubkey::new_unique();
+    let program = Pubkey::new_unique();
+    let budget_instruction = |instruction: solana_instruction::Instruction| {
+        CompiledInstruction::new_from_raw_parts(4, instruction.data, vec![])
+    };
+    let message = v0::Message {
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 3,
+        },
+        account_keys: vec![
+            payer,
+            writable,
+            program,
+            ed25519_program::id(),
+            solana_compute_budget_interface::ID,
+        ],
+        recent_blockhash: Hash::new_unique(),
+        instructions: vec![
+            budget_instruction(ComputeBudgetInstruction::set_compute_unit_limit(50_000)),
+            budget_instruction(ComputeBudgetInstruction::set_compute_unit_price(1_000)),
+            budget_instruction(
+                ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(100_000),
+            ),
+            CompiledInstruction::new_from_raw_parts(3, vec![2, 0], vec![]),
+            CompiledInstruction::new_from_raw_parts(2, vec![7; 10], vec![0, 1]),
+        ],
+        address_table_lookups: vec![],
+    };
+    let tables = HashMap::new();
+    let reserved_account_keys = HashSet::new();
+    let feature_set = FeatureSet::default();
+
+    let v0 = SanitizedV0Message::try_new(message.clone(), &tables, &reserved_account_keys).unwrap();
+    let v3 = SanitizedV3Message::try_new(
+        v3::Message::from_v0(&message),
+        &tables,
+        &reserved_account_keys,
+    )
+    .unwrap();
+    let v0_cost = TransactionCost::calculate(&v0, &feature_set);
+    let v3_cost = TransactionCost::calculate(&v3, &feature_set);
+
+    assert_eq!(
+        v0_cost,
+        TransactionCost {
+            signature_cost: SIGNATURE_COST + 2 * ED25519_VERIFY_STRICT_COST,
+            write_lock_cost: 2 * WRITE_LOCK_UNITS,
+            // 5 + 9 + 5 bytes of budget instructions, 12 bytes of the others
+            data_bytes_cost: 31 / INSTRUCTION_DATA_BYTES_COST,
+            programs_execution_cost: 50_000,
+            loaded_accounts_data_size_cost: 4 * HEAP_COST,
+        }
+    );
+    // the header carries the same budget without any instruction data
+    assert_eq!(
+        v3_cost,
+        TransactionCost {
+            data_bytes_cost: 12 / INSTRUCTION_DATA_BYTES_COST,
+            ..v0_cost
+        }
+    );
+    assert!(v3_cost.sum() < v0_cost.sum());
+
+    // without a budget, the default limit depends on which instructions
+    // invoke builtins and the full loaded accounts data size is charged
+    let legacy = SanitizedLegacyMessage::try_new(
+        legacy::Message {
+            header: message.header,
+            account_keys: message.account_keys.clone(),
+            recent_blockhash: message.recent_blockhash,
+            instructions: message.instructions[3..].to_vec(),
+        },
+        &tables,
+        &reserved_account_keys,
+    )
+    .unwrap();
+    let legacy_cost = TransactionCost::calculate(&legacy, &feature_set);
+    assert_eq!(
+        legacy_cost.programs_execution_cost,
+        2 * u64::from(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT)
+    );
+    assert_eq!(legacy_cost.loaded_accounts_data_size_cost, 2048 * HEAP_COST);
+    assert_eq!(
+        TransactionCost::calculate(&legacy, &FeatureSet::all_enabled()).programs_execution_cost,
+        u64::from(
+            MAX_BUILTIN_ALLOCATION_COMPUTE_UNIT_LIMIT + DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT
+        )
+    );
+}
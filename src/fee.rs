@@ -0,0 +1,272 @@
+//! Fees charged for a message, and the fee cap the v3 header family carries.
+//!
+//! A message pays a fee per signature plus a prioritization fee: its compute
+//! unit price times its compute unit limit, in micro-lamports, rounded up to
+//! whole lamports. With `max_fee_lamports` set, a total above the cap fails
+//! the calculation. A cap without a price sets the price itself, to the highest
+//! one whose total fits, so wallets can offer to pay up to a fixed amount
+//! without working out a price. A message whose price and limit alone cost
+//! more than its cap can never pay, and is rejected when it is sanitized.
+
+use core::fmt;
+
+use crate::{
+    builtins::FeatureSet, compute_budget::ComputeBudgetRequest, cost::num_precompile_signatures,
+    sanitized::SanitizedMessageInfo,
+};
+
+pub const MICRO_LAMPORTS_PER_LAMPORT: u64 = 1_000_000;
+/// Fee for each transaction and precompile signature.
+pub const DEFAULT_LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// The components of a message's fee, in lamports.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FeeDetails {
+    /// Transaction signatures and precompile signatures.
+    pub transaction_fee: u64,
+    pub prioritization_fee: u64,
+}
+
+/// The fee a message would pay is more than its `max_fee_lamports`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxFeeExceeded {
+    pub fee: u64,
+    pub max_fee_lamports: u64,
+}
+
+impl std::error::Error for MaxFeeExceeded {}
+
+impl fmt::Display for MaxFeeExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "fee of {} lamports exceeds the max fee of {} lamports",
+            self.fee, self.max_fee_lamports
+        )
+    }
+}
+
+impl FeeDetails {
+    /// Calculate the fee for `message` with the compute unit limit the
+    /// runtime would apply under `feature_set`, checking it against the
+    /// message's fee cap.
+    pub fn calculate<T: SanitizedMessageInfo + ?Sized>(
+        message: &T,
+        feature_set: &FeatureSet,
+        lamports_per_signature: u64,
+    ) -> Result<Self, MaxFeeExceeded> {
+        let account_keys = message.account_keys();
+        let program_instructions = || {
+            message.instructions().iter().map(|instruction| {
+                (
+                    &account_keys[usize::from(instruction.program_id_index)],
+                    instruction,
+                )
+            })
+        };
+        let compute_budget_request = message.compute_budget_request();
+        let compute_unit_limit = compute_budget_request.resolve_compute_unit_limit(
+            program_instructions().map(|(program_id, _)| program_id),
+            feature_set,
+        );
+
+        let num_signatures = program_instructions().fold(
+            message.num_required_signatures() as u64,
+            |num_signatures, (program_id, instruction)| {
+                num_signatures + num_precompile_signatures(program_id, instruction)
+            },
+        );
+        let transaction_fee = num_signatures.saturating_mul(lamports_per_signature);
+        let compute_unit_price = match (
+            compute_budget_request.compute_unit_price,
+            compute_budget_request.max_fee_lamports,
+        ) {
+            (Some(compute_unit_price), _) => compute_unit_price,
+            (None, Some(max_fee_lamports)) => max_compute_unit_price(
+                max_fee_lamports.saturating_sub(transaction_fee),
+                compute_unit_limit,
+            ),
+            (None, None) => 0,
+        };
+
+        let fee_details = Self {
+            transaction_fee,
+            prioritization_fee: prioritization_fee(compute_unit_price, compute_unit_limit),
+        };
+        match compute_budget_request.max_fee_lamports {
+            Some(max_fee_lamports) if fee_details.total_fee() > max_fee_lamports => {
+                Err(MaxFeeExceeded {
+                    fee: fee_details.total_fee(),
+                    max_fee_lamports,
+                })
+            }
+            _ => Ok(fee_details),
+        }
+    }
+
+    pub fn total_fee(&self) -> u64 {
+        self.transaction_fee.saturating_add(self.prioritization_fee)
+    }
+}
+
+impl ComputeBudgetRequest {
+    /// Check that the requested price and limit, when both are set, don't
+    /// cost more than `max_fee_lamports` before any signature is paid for.
+    pub fn check_max_fee(&self) -> Result<(), MaxFeeExceeded> {
+        if let (Some(compute_unit_price), Some(compute_unit_limit), Some(max_fee_lamports)) = (
+            self.compute_unit_price,
+            self.compute_unit_limit,
+            self.max_fee_lamports,
+        ) {
+            let fee = prioritization_fee(compute_unit_price, compute_unit_limit);
+            if fee > max_fee_lamports {
+                return Err(MaxFeeExceeded {
+                    fee,
+                    max_fee_lamports,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn prioritization_fee(compute_unit_price: u64, compute_unit_limit: u32) -> u64 {
+    let micro_lamports = u128::from(compute_unit_price) * u128::from(compute_unit_limit);
+    let lamports = micro_lamports.div_ceil(u128::from(MICRO_LAMPORTS_PER_LAMPORT));
+    u64::try_from(lamports).unwrap_or(u64::MAX)
+}
+
+/// The highest price at which `compute_unit_limit` units cost at most
+/// `lamports`.
+fn max_compute_unit_price(lamports: u64, compute_unit_limit: u32) -> u64 {
+    if compute_unit_limit == 0 {
+        return 0;
+    }
+    let micro_lamports = u128::from(lamports) * u128::from(MICRO_LAMPORTS_PER_LAMPORT);
+    u64::try_from(micro_lamports / u128::from(compute_unit_limit)).unwrap_or(u64::MAX)
+}
+
+#[test]
+fn test_fee_details() {
+    use std::collections::{HashMap, HashSet};
+
+    use solana_hash::Hash;
+    use solana_message::{MessageHeader, compiled_instruction::CompiledInstruction, v0};
+    use solana_pubkey::Pubkey;
+
+    use crate::{
+        sanitized::{SanitizedV0Message, SanitizedV3Message},
+        v3,
+    };
+
+    let message = v0::Message {
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 2,
+        },
+        account_keys: vec![
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            solana_sdk_ids::ed25519_program::id(),
+        ],
+        recent_blockhash: Hash::new_unique(),
+        instructions: vec![
+            CompiledInstruction::new_from_raw_parts(2, vec![2, 0], vec![]),
+            CompiledInstruction::new_from_raw_parts(1, vec![], vec![0]),
+        ],
+        address_table_lookups: vec![],
+    };
+    let tables = HashMap::new();
+    let reserved_account_keys = HashSet::new();
+    let feature_set = FeatureSet::default();
+    let sanitize = |header: v3::ComputeBudgetHeader| {
+        SanitizedV3Message::try_new(
+            v3::Message {
+                compute_budget_header: header,
+                header: message.header,
+                account_keys: message.account_keys.clone(),
+                recent_blockhash: message.recent_blockhash,
+                instructions: message.instructions.clone(),
+                address_table_lookups: vec![],
+            },
+            &tables,
+            &reserved_account_keys,
+        )
+    };
+    let calculate = |header: v3::ComputeBudgetHeader| {
+        let message = sanitize(header).unwrap();
+        FeeDetails::calculate(&message, &feature_set, DEFAULT_LAMPORTS_PER_SIGNATURE)
+    };
+
+    // the transaction signature and both precompile signatures pay, and the
+    // prioritization fee rounds up to whole lamports
+    let priced = v3::ComputeBudgetHeader::new(Some(200_001), Some(1_000_000), None, None);
+    let expected = FeeDetails {
+        transaction_fee: 3 * DEFAULT_LAMPORTS_PER_SIGNATURE,
+        prioritization_fee: 200_001,
+    };
+    assert_eq!(calculate(priced), Ok(expected));
+    let rounded = v3::ComputeBudgetHeader::new(Some(3), Some(1), None, None);
+    assert_eq!(calculate(rounded).unwrap().prioritization_fee, 1);
+
+    // a cap at or above the total changes nothing, below it the fee is refused
+    let total = expected.total_fee();
+    assert_eq!(
+        calculate(priced.with_max_fee_lamports(Some(total))),
+        Ok(expected)
+    );
+    assert_eq!(
+        calculate(priced.with_max_fee_lamports(Some(total - 1))),
+        Err(MaxFeeExceeded {
+            fee: total,
+            max_fee_lamports: total - 1,
+        })
+    );
+
+    // a price and limit that can't fit under the cap don't sanitize at all
+    let over = priced.with_max_fee_lamports(Some(expected.prioritization_fee - 1));
+    assert_eq!(
+        sanitize(over),
+        Err(solana_transaction_error::TransactionError::SanitizeFailure)
+    );
+    assert_eq!(
+        ComputeBudgetRequest {
+            compute_unit_limit: Some(200_001),
+            compute_unit_price: Some(1_000_000),
+            max_fee_lamports: Some(200_000),
+            ..ComputeBudgetRequest::default()
+        }
+        .check_max_fee(),
+        Err(MaxFeeExceeded {
+            fee: 200_001,
+            max_fee_lamports: 200_000,
+        })
+    );
+    assert_eq!(
+        sanitize(priced.with_max_fee_lamports(Some(expected.prioritization_fee))).map(|_| ()),
+        Ok(())
+    );
+
+    // a cap without a price pays as much of it as the limit allows, and with
+    // no limit requested the default limit applies
+    let capped =
+        v3::ComputeBudgetHeader::new(None, None, None, None).with_max_fee_lamports(Some(100_000));
+    let fee_details = calculate(capped).unwrap();
+    assert_eq!(fee_details.transaction_fee, expected.transaction_fee);
+    assert_eq!(fee_details.total_fee(), 100_000);
+    // the derived price never rounds the fee up past what's left of the cap
+    assert_eq!(prioritization_fee(max_compute_unit_price(1, 3), 3), 1);
+    let too_low = capped.with_max_fee_lamports(Some(DEFAULT_LAMPORTS_PER_SIGNATURE));
+    assert!(calculate(too_low).is_err());
+
+    // messages without a header pay the same for the same budget
+    let v0 = SanitizedV0Message::try_new(message.clone(), &tables, &reserved_account_keys).unwrap();
+    assert_eq!(
+        FeeDetails::calculate(&v0, &feature_set, DEFAULT_LAMPORTS_PER_SIGNATURE)
+            .unwrap()
+            .transaction_fee,
+        expected.transaction_fee
+    );
+    assert_eq!(prioritization_fee(u64::MAX, u32::MAX), u64::MAX);
+}
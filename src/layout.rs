@@ -0,0 +1,500 @@
+//! Byte-level layout of every message version, from the version prefix to the
+//! legacy signature counters, and for v3b on to its trailing compute budget
+//! header.
+//!
+//! Each version is described as its fields in wire order, so the order the
+//! protocol promises is written down once instead of following from struct
+//! field order. [`MessageLayout::field_ranges`] walks serialized bytes with
+//! nothing but this description, which lets tests check every encoder against
+//! it, and [`markdown`] renders the tables in `docs/layout.md`. Everything
+//! after the counters (account keys, blockhash, instructions and lookups) is
+//! laid out as in v0 for every version, and only appears in a layout as a
+//! [`FieldSize::Body`] when fields follow it.
+
+use core::ops::Range;
+
+use crate::{
+    v3::ComputeBudgetFlags,
+    v4::MAX_VARINT_LEN,
+    versioned::{MessageVersion, VersionedMessage},
+};
+
+/// How many bytes a field takes on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldSize {
+    /// Always this many bytes, little-endian for integers.
+    Fixed(usize),
+    /// [`ComputeBudgetFlags`], 1 to [`ComputeBudgetFlags::MAX_SERIALIZED_SIZE`]
+    /// bytes.
+    Flags,
+    /// A LEB128 varint, 1 to [`MAX_VARINT_LEN`] bytes.
+    VarInt,
+    /// The account keys, blockhash, instructions and address table lookups,
+    /// as in v0. Finding its end means reading every length prefix in it.
+    Body,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    pub size: FieldSize,
+    /// The flag that has to be set for the field to be present. Fields
+    /// without one are always present.
+    pub flag: Option<ComputeBudgetFlags>,
+}
+
+const fn always(name: &'static str, size: usize) -> Field {
+    Field {
+        name,
+        size: FieldSize::Fixed(size),
+        flag: None,
+    }
+}
+
+const fn flagged(name: &'static str, size: FieldSize, flag: ComputeBudgetFlags) -> Field {
+    Field {
+        name,
+        size,
+        flag: Some(flag),
+    }
+}
+
+const PREFIX: Field = always("prefix", 1);
+const FLAGS: Field = Field {
+    name: "flags",
+    size: FieldSize::Flags,
+    flag: None,
+};
+const NUM_REQUIRED_SIGNATURES: Field = always("num_required_signatures", 1);
+const NUM_READONLY_SIGNED_ACCOUNTS: Field = always("num_readonly_signed_accounts", 1);
+const NUM_READONLY_UNSIGNED_ACCOUNTS: Field = always("num_readonly_unsigned_accounts", 1);
+#[cfg(feature = "v3b")]
+const BODY: Field = Field {
+    name: "body",
+    size: FieldSize::Body,
+    flag: None,
+};
+
+const LEGACY: &[Field] = &[
+    NUM_REQUIRED_SIGNATURES,
+    NUM_READONLY_SIGNED_ACCOUNTS,
+    NUM_READONLY_UNSIGNED_ACCOUNTS,
+];
+
+const V0: &[Field] = &[
+    PREFIX,
+    NUM_REQUIRED_SIGNATURES,
+    NUM_READONLY_SIGNED_ACCOUNTS,
+    NUM_READONLY_UNSIGNED_ACCOUNTS,
+];
+
+const V1: &[Field] = &[
+    PREFIX,
+    always("compute_unit_price", 8),
+    always("compute_unit_limit", 4),
+    NUM_REQUIRED_SIGNATURES,
+    NUM_READONLY_SIGNED_ACCOUNTS,
+    NUM_READONLY_UNSIGNED_ACCOUNTS,
+];
+
+const V2: &[Field] = &[
+    PREFIX,
+    always("compute_unit_price", 8),
+    always("compute_unit_limit", 4),
+    always("loaded_accounts_data_limit", 4),
+    always("requested_heap_bytes", 4),
+    NUM_REQUIRED_SIGNATURES,
+    NUM_READONLY_SIGNED_ACCOUNTS,
+    NUM_READONLY_UNSIGNED_ACCOUNTS,
+];
+
+const V3: &[Field] = &[
+    PREFIX,
+    FLAGS,
+    flagged(
+        "compute_unit_limit",
+        FieldSize::Fixed(4),
+        ComputeBudgetFlags::COMPUTE_UNIT_LIMIT,
+    ),
+    flagged(
+        "compute_unit_price",
+        FieldSize::Fixed(8),
+        ComputeBudgetFlags::COMPUTE_UNIT_PRICE,
+    ),
+    flagged(
+        "loaded_accounts_data_limit",
+        FieldSize::Fixed(4),
+        ComputeBudgetFlags::LOADED_ACCOUNTS_DATA_LIMIT,
+    ),
+    flagged(
+        "requested_heap_bytes_limit",
+        FieldSize::Fixed(4),
+        ComputeBudgetFlags::REQUESTED_HEAP_BYTES_LIMIT,
+    ),
+    flagged(
+        "max_fee_lamports",
+        FieldSize::Fixed(8),
+        ComputeBudgetFlags::MAX_FEE_LAMPORTS,
+    ),
+    NUM_REQUIRED_SIGNATURES,
+    NUM_READONLY_SIGNED_ACCOUNTS,
+    NUM_READONLY_UNSIGNED_ACCOUNTS,
+];
+
+const V4: &[Field] = &[
+    PREFIX,
+    FLAGS,
+    flagged(
+        "compute_unit_limit",
+        FieldSize::VarInt,
+        ComputeBudgetFlags::COMPUTE_UNIT_LIMIT,
+    ),
+    flagged(
+        "compute_unit_price",
+        FieldSize::VarInt,
+        ComputeBudgetFlags::COMPUTE_UNIT_PRICE,
+    ),
+    flagged(
+        "loaded_accounts_data_limit",
+        FieldSize::VarInt,
+        ComputeBudgetFlags::LOADED_ACCOUNTS_DATA_LIMIT,
+    ),
+    flagged(
+        "requested_heap_bytes_limit",
+        FieldSize::VarInt,
+        ComputeBudgetFlags::REQUESTED_HEAP_BYTES_LIMIT,
+    ),
+    flagged(
+        "max_fee_lamports",
+        FieldSize::VarInt,
+        ComputeBudgetFlags::MAX_FEE_LAMPORTS,
+    ),
+    NUM_REQUIRED_SIGNATURES,
+    NUM_READONLY_SIGNED_ACCOUNTS,
+    NUM_READONLY_UNSIGNED_ACCOUNTS,
+];
+
+const V5: &[Field] = &[
+    PREFIX,
+    FLAGS,
+    flagged(
+        "compute_unit_limit",
+        FieldSize::Fixed(4),
+        ComputeBudgetFlags::COMPUTE_UNIT_LIMIT,
+    ),
+    flagged(
+        "compute_unit_price.exponent",
+        FieldSize::Fixed(1),
+        ComputeBudgetFlags::COMPUTE_UNIT_PRICE,
+    ),
+    flagged(
+        "compute_unit_price.mantissa",
+        FieldSize::Fixed(4),
+        ComputeBudgetFlags::COMPUTE_UNIT_PRICE,
+    ),
+    flagged(
+        "loaded_accounts_data_limit",
+        FieldSize::Fixed(4),
+        ComputeBudgetFlags::LOADED_ACCOUNTS_DATA_LIMIT,
+    ),
+    flagged(
+        "requested_heap_bytes_limit",
+        FieldSize::Fixed(4),
+        ComputeBudgetFlags::REQUESTED_HEAP_BYTES_LIMIT,
+    ),
+    flagged(
+        "max_fee_lamports",
+        FieldSize::Fixed(8),
+        ComputeBudgetFlags::MAX_FEE_LAMPORTS,
+    ),
+    NUM_REQUIRED_SIGNATURES,
+    NUM_READONLY_SIGNED_ACCOUNTS,
+    NUM_READONLY_UNSIGNED_ACCOUNTS,
+];
+
+#[cfg(feature = "v3b")]
+const V3B: &[Field] = &[
+    PREFIX,
+    NUM_REQUIRED_SIGNATURES,
+    NUM_READONLY_SIGNED_ACCOUNTS,
+    NUM_READONLY_UNSIGNED_ACCOUNTS,
+    BODY,
+    FLAGS,
+    flagged(
+        "compute_unit_limit",
+        FieldSize::Fixed(4),
+        ComputeBudgetFlags::COMPUTE_UNIT_LIMIT,
+    ),
+    flagged(
+        "compute_unit_price",
+        FieldSize::Fixed(8),
+        ComputeBudgetFlags::COMPUTE_UNIT_PRICE,
+    ),
+    flagged(
+        "loaded_accounts_data_limit",
+        FieldSize::Fixed(4),
+        ComputeBudgetFlags::LOADED_ACCOUNTS_DATA_LIMIT,
+    ),
+    flagged(
+        "requested_heap_bytes_limit",
+        FieldSize::Fixed(4),
+        ComputeBudgetFlags::REQUESTED_HEAP_BYTES_LIMIT,
+    ),
+    flagged(
+        "max_fee_lamports",
+        FieldSize::Fixed(8),
+        ComputeBudgetFlags::MAX_FEE_LAMPORTS,
+    ),
+];
+
+/// The fields at the start of a serialized message of one version, in wire
+/// order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageLayout {
+    pub version: MessageVersion,
+    pub fields: &'static [Field],
+}
+
+impl MessageLayout {
+    pub fn of(version: MessageVersion) -> MessageLayout {
+        let fields = match version {
+            MessageVersion::Legacy => LEGACY,
+            MessageVersion::V0 => V0,
+            MessageVersion::V1 => V1,
+            MessageVersion::V2 => V2,
+            MessageVersion::V3 => V3,
+            MessageVersion::V4 => V4,
+            MessageVersion::V5 => V5,
+            #[cfg(feature = "v3b")]
+            MessageVersion::V3b => V3B,
+        };
+        MessageLayout { version, fields }
+    }
+
+    /// Offset of `name` from the start of the message, if no field before it
+    /// can change size or be left out.
+    pub fn fixed_offset(&self, name: &str) -> Option<usize> {
+        let mut offset = 0;
+        for field in self.fields {
+            if field.name == name {
+                return Some(offset);
+            }
+            match (field.size, field.flag) {
+                (FieldSize::Fixed(size), None) => offset += size,
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// The byte range of each field present in `bytes`, a serialized message
+    /// of this version, found using only the layout. `None` if the bytes end
+    /// early or the flags don't decode.
+    pub fn field_ranges(&self, bytes: &[u8]) -> Option<Vec<(&'static str, Range<usize>)>> {
+        let mut flags = ComputeBudgetFlags::empty();
+        let mut offset = 0;
+        let mut ranges = Vec::with_capacity(self.fields.len());
+        for field in self.fields {
+            if field.flag.is_some_and(|flag| !flags.contains(flag)) {
+                continue;
+            }
+            let rest = bytes.get(offset..)?;
+            let len = match field.size {
+                FieldSize::Fixed(size) => size,
+                FieldSize::Flags => {
+                    let len = continued_len(rest, ComputeBudgetFlags::MAX_SERIALIZED_SIZE)?;
+                    flags = ComputeBudgetFlags::from_bytes(&rest[..len]).ok()?;
+                    len
+                }
+                FieldSize::VarInt => continued_len(rest, MAX_VARINT_LEN)?,
+                FieldSize::Body => body_len(rest)?,
+            };
+            bytes.get(offset..offset + len)?;
+            ranges.push((field.name, offset..offset + len));
+            offset += len;
+        }
+        Some(ranges)
+    }
+
+    /// [`Self::field_ranges`] of `message` as [`VersionedMessage::serialize`]
+    /// encodes it.
+    pub fn message_field_ranges(message: &VersionedMessage) -> Vec<(&'static str, Range<usize>)> {
+        MessageLayout::of(message.version())
+            .field_ranges(&message.serialize())
+            .expect("serialized messages match their layout")
+    }
+
+    /// A markdown table of the fields, their offsets and sizes.
+    pub fn to_markdown(&self) -> String {
+        let mut table = String::from(
+            "| Field | Offset | Size (bytes) | Present |\n\
+             | ----- | ------ | ------------ | ------- |\n",
+        );
+        let mut previous = None;
+        for field in self.fields {
+            let offset = match (self.fixed_offset(field.name), previous) {
+                (Some(offset), _) => offset.to_string(),
+                (None, Some(previous)) => format!("after `{previous}`"),
+                (None, None) => unreachable!("the first field is always at offset 0"),
+            };
+            let size = match field.size {
+                FieldSize::Fixed(size) => size.to_string(),
+                FieldSize::Flags => format!("1–{}", ComputeBudgetFlags::MAX_SERIALIZED_SIZE),
+                FieldSize::VarInt => format!("1–{MAX_VARINT_LEN}"),
+                FieldSize::Body => "variable".to_string(),
+            };
+            let present = match field.flag.and_then(|flag| flag.iter_names().next()) {
+                Some((name, _)) => format!("`{name}` flag"),
+                None => "always".to_string(),
+            };
+            table += &format!("| `{}` | {offset} | {size} | {present} |\n", field.name);
+            previous = Some(field.name);
+        }
+        table
+    }
+}
+
+/// Length of a run of bytes where every byte but the last has its high bit
+/// set, as in both the flags and varint encodings.
+fn continued_len(bytes: &[u8], max_len: usize) -> Option<usize> {
+    bytes
+        .iter()
+        .take(max_len)
+        .position(|byte| byte & 0x80 == 0)
+        .map(|index| index + 1)
+}
+
+/// Length of the message body at the start of `bytes`: the account keys,
+/// blockhash, instructions and address table lookups, each list preceded by
+/// its short_vec length.
+fn body_len(bytes: &[u8]) -> Option<usize> {
+    let mut offset = 0;
+    let num_account_keys = short_vec_len(bytes, &mut offset)?;
+    offset += num_account_keys * 32 + 32;
+    for _ in 0..short_vec_len(bytes, &mut offset)? {
+        // program id index, then the account indexes and data
+        offset += 1;
+        offset += short_vec_len(bytes, &mut offset)?;
+        offset += short_vec_len(bytes, &mut offset)?;
+    }
+    for _ in 0..short_vec_len(bytes, &mut offset)? {
+        // table key, then the writable and readonly indexes
+        offset += 32;
+        offset += short_vec_len(bytes, &mut offset)?;
+        offset += short_vec_len(bytes, &mut offset)?;
+    }
+    bytes.get(..offset).map(<[u8]>::len)
+}
+
+/// Decode the short_vec length at `offset` and move past it. Like
+/// `solana_short_vec`, only the shortest encoding of a `u16` decodes.
+fn short_vec_len(bytes: &[u8], offset: &mut usize) -> Option<usize> {
+    let rest = bytes.get(*offset..)?;
+    let len = continued_len(rest, 3)?;
+    let last = rest[len - 1];
+    if (len > 1 && last == 0) || (len == 3 && last > 0b11) {
+        return None;
+    }
+    *offset += len;
+    Some(
+        rest[..len]
+            .iter()
+            .enumerate()
+            .fold(0, |value, (index, byte)| {
+                value | usize::from(byte & 0x7f) << (7 * index)
+            }),
+    )
+}
+
+/// The layout of every version, as the markdown checked in at
+/// `docs/layout.md`.
+pub fn markdown() -> String {
+    let mut doc = String::from(
+        "# Message layouts\n\n\
+         <!-- markdownlint-disable MD013 -->\n\n\
+         Generated from `v1tx::layout`, do not edit. Each table covers a\n\
+         message from its first byte to the legacy signature counters; the\n\
+         account keys, blockhash, instructions and address table lookups\n\
+         follow as in v0, shown as `body` where more fields come after them.\n\
+         Integers are little-endian. Fields with a flag are only present when\n\
+         that bit of `flags` is set, in the order listed.\n",
+    );
+    for &version in MessageVersion::ALL {
+        doc += &format!(
+            "\n## {version}\n\n{}",
+            MessageLayout::of(version).to_markdown()
+        );
+    }
+    doc
+}
+
+#[test]
+fn test_message_layout() {
+    // the fixed-size headers put the new fields right before the counters
+    let v1 = MessageLayout::of(MessageVersion::V1);
+    assert_eq!(v1.fixed_offset("compute_unit_price"), Some(1));
+    assert_eq!(v1.fixed_offset("compute_unit_limit"), Some(9));
+    assert_eq!(v1.fixed_offset("num_required_signatures"), Some(13));
+    let v2 = MessageLayout::of(MessageVersion::V2);
+    assert_eq!(v2.fixed_offset("requested_heap_bytes"), Some(17));
+    assert_eq!(v2.fixed_offset("num_required_signatures"), Some(21));
+    assert_eq!(
+        MessageLayout::of(MessageVersion::Legacy).fixed_offset("num_required_signatures"),
+        Some(0)
+    );
+
+    // past the flags nothing has a fixed offset
+    let v3 = MessageLayout::of(MessageVersion::V3);
+    assert_eq!(v3.fixed_offset("flags"), Some(1));
+    assert_eq!(v3.fixed_offset("compute_unit_limit"), None);
+
+    // absent fields take no bytes, and truncated bytes have no layout
+    let bytes = [0x83, 0b10, 1, 2, 3, 4, 5, 6, 7, 8, 1, 0, 0];
+    assert_eq!(
+        v3.field_ranges(&bytes),
+        Some(vec![
+            ("prefix", 0..1),
+            ("flags", 1..2),
+            ("compute_unit_price", 2..10),
+            ("num_required_signatures", 10..11),
+            ("num_readonly_signed_accounts", 11..12),
+            ("num_readonly_unsigned_accounts", 12..13),
+        ])
+    );
+    assert_eq!(v3.field_ranges(&bytes[..12]), None);
+    assert_eq!(v3.field_ranges(&[0x83, 0b100000]), None);
+    let v4 = MessageLayout::of(MessageVersion::V4);
+    assert_eq!(
+        v4.field_ranges(&[0x84, 0b1, 0x80, 0x01, 1, 0, 0]).unwrap()[2],
+        ("compute_unit_limit", 2..4)
+    );
+
+    // v3b keeps the counters where v0 has them, and has to walk the body to
+    // find its budget
+    #[cfg(feature = "v3b")]
+    {
+        let v3b = MessageLayout::of(MessageVersion::V3b);
+        assert_eq!(v3b.fixed_offset("num_readonly_unsigned_accounts"), Some(3));
+        assert_eq!(v3b.fixed_offset("flags"), None);
+        // one key, the blockhash, one instruction with an account and 2 bytes
+        // of data, and no lookups
+        let mut bytes = vec![0x86, 1, 0, 0, 1];
+        bytes.extend([7; 64]);
+        bytes.extend([1, 0, 1, 0, 2, 9, 9, 0]);
+        bytes.extend([0b1, 1, 0, 0, 0]);
+        let ranges = v3b.field_ranges(&bytes).unwrap();
+        assert_eq!(ranges[4], ("body", 4..77));
+        assert_eq!(ranges[5], ("flags", 77..78));
+        assert_eq!(ranges[6], ("compute_unit_limit", 78..82));
+        assert_eq!(v3b.field_ranges(&bytes[..76]), None);
+        assert_eq!(v3b.field_ranges(&bytes[..81]), None);
+
+        // lengths the short_vec decoder rejects don't get a layout either
+        let mut padded = bytes.clone();
+        padded.splice(4..5, [0x81, 0]);
+        assert_eq!(v3b.field_ranges(&padded), None);
+        assert_eq!(short_vec_len(&[0xff, 0xff, 0b11], &mut 0), Some(0xffff));
+        assert_eq!(short_vec_len(&[0xff, 0xff, 0b100], &mut 0), None);
+        assert_eq!(short_vec_len(&[0x80, 0x80, 0b1], &mut 0), Some(1 << 14));
+    }
+}
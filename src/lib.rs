@@ -4,6 +4,98 @@ use serde_derive::{Deserialize, Serialize};
 use solana_hash::Hash;
 use solana_message::{compiled_instruction::CompiledInstruction, v0::MessageAddressTableLookup};
 use solana_pubkey::Pubkey;
+use solana_sanitize::SanitizeError;
+
+pub mod account_roles;
+#[cfg(feature = "serde")]
+pub mod batch;
+pub mod builtins;
+pub mod compute_budget;
+pub mod convert;
+#[cfg(feature = "serde")]
+pub mod cost;
+#[cfg(feature = "serde")]
+pub mod fee;
+#[cfg(feature = "serde")]
+pub mod layout;
+pub mod loaded;
+#[cfg(feature = "serde")]
+pub mod sanitized;
+#[cfg(feature = "signer")]
+pub mod signing;
+pub mod svm;
+#[cfg(feature = "serde")]
+pub mod transaction;
+#[cfg(feature = "serde")]
+pub mod versioned;
+
+/// Sanitize the parts of a message that are shared by every version in this
+/// crate. These are the same rules `v0::Message::sanitize` applies.
+fn sanitize_message(
+    header: &solana_message::MessageHeader,
+    account_keys: &[Pubkey],
+    instructions: &[CompiledInstruction],
+    address_table_lookups: &[MessageAddressTableLookup],
+) -> Result<(), SanitizeError> {
+    let num_static_account_keys = account_keys.len();
+    if usize::from(header.num_required_signatures)
+        .saturating_add(usize::from(header.num_readonly_unsigned_accounts))
+        > num_static_account_keys
+    {
+        return Err(SanitizeError::IndexOutOfBounds);
+    }
+
+    // there should be at least 1 RW fee-payer account.
+    if header.num_readonly_signed_accounts >= header.num_required_signatures {
+        return Err(SanitizeError::InvalidValue);
+    }
+
+    let mut num_dynamic_account_keys: usize = 0;
+    for lookup in address_table_lookups {
+        let num_lookup_indexes = lookup
+            .writable_indexes
+            .len()
+            .saturating_add(lookup.readonly_indexes.len());
+
+        // each lookup table must be used to load at least one account
+        if num_lookup_indexes == 0 {
+            return Err(SanitizeError::InvalidValue);
+        }
+
+        num_dynamic_account_keys = num_dynamic_account_keys.saturating_add(num_lookup_indexes);
+    }
+
+    if num_static_account_keys == 0 {
+        return Err(SanitizeError::InvalidValue);
+    }
+
+    // account indices are encoded as `u8`
+    let total_account_keys = num_static_account_keys.saturating_add(num_dynamic_account_keys);
+    if total_account_keys > 256 {
+        return Err(SanitizeError::IndexOutOfBounds);
+    }
+
+    let max_account_ix = total_account_keys - 1;
+    // program ids can't be loaded from lookup tables
+    let max_program_id_ix = num_static_account_keys - 1;
+
+    for ci in instructions {
+        if usize::from(ci.program_id_index) > max_program_id_ix {
+            return Err(SanitizeError::IndexOutOfBounds);
+        }
+        // A program cannot be a payer.
+        if ci.program_id_index == 0 {
+            return Err(SanitizeError::IndexOutOfBounds);
+        }
+        for ai in &ci.accounts {
+            if usize::from(*ai) > max_account_ix {
+                return Err(SanitizeError::IndexOutOfBounds);
+            }
+        }
+    }
+
+    Ok(())
+}
 
 // Add CU price/limit to header
 pub mod v1 {
@@ -14,6 +106,7 @@ pub mod v1 {
         derive(Deserialize, Serialize),
         serde(rename_all = "camelCase")
     )]
+    #[derive(Debug, Clone, PartialEq, Eq)]
     pub struct Message {
         pub header: MessageHeader,
 
@@ -34,6 +127,7 @@ pub mod v1 {
         derive(Deserialize, Serialize),
         serde(rename_all = "camelCase")
     )]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct MessageHeader {
         /* NEW FIELD */
         pub compute_unit_price: u64,
@@ -43,6 +137,139 @@ pub mod v1 {
         pub num_readonly_signed_accounts: u8,
         pub num_readonly_unsigned_accounts: u8,
     }
+
+    impl MessageHeader {
+        /// The legacy signature counters carried by this header.
+        pub fn as_message_header(&self) -> solana_message::MessageHeader {
+            solana_message::MessageHeader {
+                num_required_signatures: self.num_required_signatures,
+                num_readonly_signed_accounts: self.num_readonly_signed_accounts,
+                num_readonly_unsigned_accounts: self.num_readonly_unsigned_accounts,
+            }
+        }
+    }
+
+    impl From<MessageHeader> for solana_message::MessageHeader {
+        fn from(header: MessageHeader) -> Self {
+            header.as_message_header()
+        }
+    }
+
+    impl Message {
+        /// Sanitize message fields and compiled instruction indexes
+        pub fn sanitize(&self) -> Result<(), SanitizeError> {
+            sanitize_message(
+                &self.header.as_message_header(),
+                &self.account_keys,
+                &self.instructions,
+                &self.address_table_lookups,
+            )
+        }
+    }
+
+    /// [`MessageHeader`] exactly as it is laid out on the wire, so a
+    /// validator can read it in place from the packet buffer instead of
+    /// deserializing the message. Fields are little-endian and the struct has
+    /// no padding or alignment, so any [`PodMessageHeader::SIZE`] bytes are a
+    /// valid header.
+    #[cfg(feature = "pod")]
+    #[repr(C)]
+    #[derive(
+        Debug,
+        Clone,
+        Copy,
+        PartialEq,
+        Eq,
+        zerocopy::FromBytes,
+        zerocopy::IntoBytes,
+        zerocopy::KnownLayout,
+        zerocopy::Immutable,
+        zerocopy::Unaligned,
+    )]
+    pub struct PodMessageHeader {
+        pub compute_unit_price: zerocopy::little_endian::U64,
+        pub compute_unit_limit: zerocopy::little_endian::U32,
+        pub num_required_signatures: u8,
+        pub num_readonly_signed_accounts: u8,
+        pub num_readonly_unsigned_accounts: u8,
+    }
+
+    #[cfg(feature = "pod")]
+    impl PodMessageHeader {
+        /// Serialized size of a v1 header.
+        pub const SIZE: usize = 8 + 4 + 3;
+
+        /// The header at the start of `bytes`, which follow the version prefix
+        /// byte of a serialized v1 message.
+        pub fn from_bytes(bytes: &[u8]) -> Option<&PodMessageHeader> {
+            zerocopy::FromBytes::ref_from_prefix(bytes)
+                .ok()
+                .map(|(header, _)| header)
+        }
+    }
+
+    #[cfg(feature = "pod")]
+    const _: () = assert!(core::mem::size_of::<PodMessageHeader>() == PodMessageHeader::SIZE);
+
+    #[cfg(feature = "pod")]
+    impl From<&PodMessageHeader> for MessageHeader {
+        fn from(header: &PodMessageHeader) -> Self {
+            MessageHeader {
+                compute_unit_price: header.compute_unit_price.get(),
+                compute_unit_limit: header.compute_unit_limit.get(),
+                num_required_signatures: header.num_required_signatures,
+                num_readonly_signed_accounts: header.num_readonly_signed_accounts,
+                num_readonly_unsigned_accounts: header.num_readonly_unsigned_accounts,
+            }
+        }
+    }
+
+    #[cfg(feature = "pod")]
+    impl From<&MessageHeader> for PodMessageHeader {
+        fn from(header: &MessageHeader) -> Self {
+            PodMessageHeader {
+                compute_unit_price: header.compute_unit_price.into(),
+                compute_unit_limit: header.compute_unit_limit.into(),
+                num_required_signatures: header.num_required_signatures,
+                num_readonly_signed_accounts: header.num_readonly_signed_accounts,
+                num_readonly_unsigned_accounts: header.num_readonly_unsigned_accounts,
+            }
+        }
+    }
+
+    #[cfg(all(feature = "pod", feature = "serde"))]
+    #[test]
+    fn test_pod_message_header() {
+        use zerocopy::IntoBytes;
+
+        let header = MessageHeader {
+            compute_unit_price: 0x0102_0304_0506_0708,
+            compute_unit_limit: 0x0a0b_0c0d,
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 2,
+        };
+        let message = Message {
+            header,
+            account_keys: vec![Pubkey::new_unique()],
+            recent_blockhash: Hash::new_unique(),
+            instructions: vec![],
+            address_table_lookups: vec![],
+        };
+        let bytes = bincode::serialize(&message).unwrap();
+
+        // the header is read in place, and matches the serialized bytes
+        let pod = PodMessageHeader::from_bytes(&bytes).unwrap();
+        assert_eq!(pod.compute_unit_price.get(), header.compute_unit_price);
+        assert_eq!(MessageHeader::from(pod), header);
+        assert_eq!(
+            PodMessageHeader::from(&header).as_bytes(),
+            &bytes[..PodMessageHeader::SIZE]
+        );
+        assert_eq!(core::mem::align_of::<PodMessageHeader>(), 1);
+        assert!(PodMessageHeader::from_bytes(&bytes[..PodMessageHeader::SIZE - 1]).is_none());
+        assert!(PodMessageHeader::from_bytes(&bytes[1..]).is_some());
+    }
 }
 
 // Add CU price/limit + loaded accounts data size + requested heap bytes size to header
@@ -54,6 +281,7 @@ pub mod v2 {
         derive(Deserialize, Serialize),
         serde(rename_all = "camelCase")
     )]
+    #[derive(Debug, Clone, PartialEq, Eq)]
     pub struct Message {
         pub header: MessageHeader,
 
@@ -74,6 +302,7 @@ pub mod v2 {
         derive(Deserialize, Serialize),
         serde(rename_all = "camelCase")
     )]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct MessageHeader {
         /* NEW FIELD */
         pub compute_unit_price: u64,
@@ -87,6 +316,141 @@ pub mod v2 {
         pub num_readonly_signed_accounts: u8,
         pub num_readonly_unsigned_accounts: u8,
     }
+
+    impl MessageHeader {
+        /// The legacy signature counters carried by this header.
+        pub fn as_message_header(&self) -> solana_message::MessageHeader {
+            solana_message::MessageHeader {
+                num_required_signatures: self.num_required_signatures,
+                num_readonly_signed_accounts: self.num_readonly_signed_accounts,
+                num_readonly_unsigned_accounts: self.num_readonly_unsigned_accounts,
+            }
+        }
+    }
+
+    impl From<MessageHeader> for solana_message::MessageHeader {
+        fn from(header: MessageHeader) -> Self {
+            header.as_message_header()
+        }
+    }
+
+    impl Message {
+        /// Sanitize message fields and compiled instruction indexes
+        pub fn sanitize(&self) -> Result<(), SanitizeError> {
+            sanitize_message(
+                &self.header.as_message_header(),
+                &self.account_keys,
+                &self.instructions,
+                &self.address_table_lookups,
+            )
+        }
+    }
+
+    /// [`MessageHeader`] exactly as it is laid out on the wire, see
+    /// [`crate::v1::PodMessageHeader`].
+    #[cfg(feature = "pod")]
+    #[repr(C)]
+    #[derive(
+        Debug,
+        Clone,
+        Copy,
+        PartialEq,
+        Eq,
+        zerocopy::FromBytes,
+        zerocopy::IntoBytes,
+        zerocopy::KnownLayout,
+        zerocopy::Immutable,
+        zerocopy::Unaligned,
+    )]
+    pub struct PodMessageHeader {
+        pub compute_unit_price: zerocopy::little_endian::U64,
+        pub compute_unit_limit: zerocopy::little_endian::U32,
+        pub loaded_accounts_data_limit: zerocopy::little_endian::U32,
+        pub requested_heap_bytes: zerocopy::little_endian::U32,
+        pub num_required_signatures: u8,
+        pub num_readonly_signed_accounts: u8,
+        pub num_readonly_unsigned_accounts: u8,
+    }
+
+    #[cfg(feature = "pod")]
+    impl PodMessageHeader {
+        /// Serialized size of a v2 header.
+        pub const SIZE: usize = 8 + 4 + 4 + 4 + 3;
+
+        /// The header at the start of `bytes`, which follow the version prefix
+        /// byte of a serialized v2 message.
+        pub fn from_bytes(bytes: &[u8]) -> Option<&PodMessageHeader> {
+            zerocopy::FromBytes::ref_from_prefix(bytes)
+                .ok()
+                .map(|(header, _)| header)
+        }
+    }
+
+    #[cfg(feature = "pod")]
+    const _: () = assert!(core::mem::size_of::<PodMessageHeader>() == PodMessageHeader::SIZE);
+
+    #[cfg(feature = "pod")]
+    impl From<&PodMessageHeader> for MessageHeader {
+        fn from(header: &PodMessageHeader) -> Self {
+            MessageHeader {
+                compute_unit_price: header.compute_unit_price.get(),
+                compute_unit_limit: header.compute_unit_limit.get(),
+                loaded_accounts_data_limit: header.loaded_accounts_data_limit.get(),
+                requested_heap_bytes: header.requested_heap_bytes.get(),
+                num_required_signatures: header.num_required_signatures,
+                num_readonly_signed_accounts: header.num_readonly_signed_accounts,
+                num_readonly_unsigned_accounts: header.num_readonly_unsigned_accounts,
+            }
+        }
+    }
+
+    #[cfg(feature = "pod")]
+    impl From<&MessageHeader> for PodMessageHeader {
+        fn from(header: &MessageHeader) -> Self {
+            PodMessageHeader {
+                compute_unit_price: header.compute_unit_price.into(),
+                compute_unit_limit: header.compute_unit_limit.into(),
+                loaded_accounts_data_limit: header.loaded_accounts_data_limit.into(),
+                requested_heap_bytes: header.requested_heap_bytes.into(),
+                num_required_signatures: header.num_required_signatures,
+                num_readonly_signed_accounts: header.num_readonly_signed_accounts,
+                num_readonly_unsigned_accounts: header.num_readonly_unsigned_accounts,
+            }
+        }
+    }
+
+    #[cfg(all(feature = "pod", feature = "serde"))]
+    #[test]
+    fn test_pod_message_header() {
+        use zerocopy::IntoBytes;
+
+        let header = MessageHeader {
+            compute_unit_price: 0x0102_0304_0506_0708,
+            compute_unit_limit: 0x0a0b_0c0d,
+            loaded_accounts_data_limit: 0x1112_1314,
+            requested_heap_bytes: 0x2122_2324,
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 2,
+        };
+        let message = Message {
+            header,
+            account_keys: vec![Pubkey::new_unique()],
+            recent_blockhash: Hash::new_unique(),
+            instructions: vec![],
+            address_table_lookups: vec![],
+        };
+        let bytes = bincode::serialize(&message).unwrap();
+
+        let pod = PodMessageHeader::from_bytes(&bytes).unwrap();
+        assert_eq!(pod.requested_heap_bytes.get(), header.requested_heap_bytes);
+        assert_eq!(MessageHeader::from(pod), header);
+        assert_eq!(
+            PodMessageHeader::from(&header).as_bytes(),
+            &bytes[..PodMessageHeader::SIZE]
+        );
+        assert!(PodMessageHeader::from_bytes(&bytes[..PodMessageHeader::SIZE - 1]).is_none());
+    }
 }
 
 // Add ComputeBudgetFlags + dynamic payload to end
@@ -103,8 +467,7 @@ pub mod v3 {
         derive(Deserialize, Serialize),
         serde(rename_all = "camelCase")
     )]
-    #[cfg_attr(test, derive(PartialEq, Eq))]
-    #[derive(Debug)]
+    #[derive(Debug, Clone, PartialEq, Eq)]
     pub struct Message {
         #[cfg_attr(feature = "serde", serde(with = "compute_budget_header_serde"))]
         pub compute_budget_header: ComputeBudgetHeader,
@@ -124,8 +487,20 @@ pub mod v3 {
         
     }
     
+    impl Message {
+        /// Sanitize message fields and compiled instruction indexes
+        pub fn sanitize(&self) -> Result<(), SanitizeError> {
+            sanitize_message(
+                &self.header,
+                &self.account_keys,
+                &self.instructions,
+                &self.address_table_lookups,
+            )
+        }
+    }
+
     // This lets us use u64s and u32 at the end of Message!
-    const _: () = assert!(core::mem::size_of::<Message>() % 8 == 0);
+    const _: () = assert!(core::mem::size_of::<Message>().is_multiple_of(8));
     const _: () = assert!(core::mem::align_of::<Message>() == 8);
 
 
@@ -135,14 +510,14 @@ pub mod v3 {
         derive(Deserialize, Serialize),
         serde(rename_all = "camelCase")
     )]
-    #[cfg_attr(test, derive(PartialEq, Eq))]
-    #[derive(Debug)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct ComputeBudgetHeader {
         flags: ComputeBudgetFlags,
         compute_unit_limit: Option<u32>,
         compute_unit_price: Option<u64>,
         loaded_accounts_data_limit: Option<u32>,
         requested_heap_bytes_limit: Option<u32>,
+        max_fee_lamports: Option<u64>,
     }
 
     impl ComputeBudgetHeader {
@@ -173,8 +548,51 @@ pub mod v3 {
                 compute_unit_price,
                 loaded_accounts_data_limit,
                 requested_heap_bytes_limit,
+                max_fee_lamports: None,
             }
         }
+
+        /// Cap the total fee the message can be charged, see
+        /// [`crate::fee::FeeDetails`].
+        pub fn with_max_fee_lamports(mut self, max_fee_lamports: Option<u64>) -> ComputeBudgetHeader {
+            self.flags.set(ComputeBudgetFlags::MAX_FEE_LAMPORTS, max_fee_lamports.is_some());
+            self.max_fee_lamports = max_fee_lamports;
+            self
+        }
+
+        pub fn flags(&self) -> ComputeBudgetFlags {
+            self.flags
+        }
+
+        pub fn compute_unit_limit(&self) -> Option<u32> {
+            self.compute_unit_limit
+        }
+
+        pub fn compute_unit_price(&self) -> Option<u64> {
+            self.compute_unit_price
+        }
+
+        pub fn loaded_accounts_data_limit(&self) -> Option<u32> {
+            self.loaded_accounts_data_limit
+        }
+
+        pub fn requested_heap_bytes_limit(&self) -> Option<u32> {
+            self.requested_heap_bytes_limit
+        }
+
+        pub fn max_fee_lamports(&self) -> Option<u64> {
+            self.max_fee_lamports
+        }
+
+        /// Number of bytes this header occupies on the wire: the flags bytes
+        /// followed by each present field.
+        pub fn serialized_size(&self) -> usize {
+            self.flags.serialized_size() + self.compute_unit_limit.map_or(0, |_| 4)
+                + self.compute_unit_price.map_or(0, |_| 8)
+                + self.loaded_accounts_data_limit.map_or(0, |_| 4)
+                + self.requested_heap_bytes_limit.map_or(0, |_| 4)
+                + self.max_fee_lamports.map_or(0, |_| 8)
+        }
     }
     
 
@@ -185,11 +603,156 @@ pub mod v3 {
             serde(rename_all = "camelCase")
         )]
         #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-        pub struct ComputeBudgetFlags: u8 {
+        pub struct ComputeBudgetFlags: u16 {
             const COMPUTE_UNIT_LIMIT         = 0b00000001;
             const COMPUTE_UNIT_PRICE         = 0b00000010;
             const LOADED_ACCOUNTS_DATA_LIMIT = 0b00000100;
             const REQUESTED_HEAP_BYTES_LIMIT = 0b00001000;
+            const MAX_FEE_LAMPORTS           = 0b00010000;
+        }
+    }
+
+    /// Flags are sent 7 bits per byte, lowest bits first. Every byte but the
+    /// last has [`ComputeBudgetFlags::CONTINUATION`] set, so fields past the
+    /// first 7 can be added without a new message version, and messages that
+    /// don't use them pay nothing.
+    impl ComputeBudgetFlags {
+        /// Set on a flags byte when another flags byte follows it.
+        pub const CONTINUATION: u8 = 0b10000000;
+        /// Flags bytes needed to carry every bit of a `u16`.
+        pub const MAX_SERIALIZED_SIZE: usize = 3;
+
+        /// Number of flags bytes on the wire.
+        pub fn serialized_size(&self) -> usize {
+            let bits = u16::BITS - self.bits().leading_zeros();
+            (bits.div_ceil(7) as usize).max(1)
+        }
+
+        pub fn to_bytes(&self) -> Vec<u8> {
+            let len = self.serialized_size();
+            (0..len)
+                .map(|index| {
+                    let byte = (self.bits() >> (7 * index)) as u8 & !Self::CONTINUATION;
+                    if index + 1 < len { byte | Self::CONTINUATION } else { byte }
+                })
+                .collect()
+        }
+
+        /// Decode the flags bytes at the start of a header. Only the shortest
+        /// encoding decodes, and any bit that isn't a known field is
+        /// rejected, whichever byte it is in.
+        pub fn from_bytes(bytes: &[u8]) -> Result<Self, InvalidFlags> {
+            Self::from_bytes_with_known(bytes, Self::all())
+        }
+
+        fn from_bytes_with_known(bytes: &[u8], known: Self) -> Result<Self, InvalidFlags> {
+            if bytes.len() > Self::MAX_SERIALIZED_SIZE {
+                return Err(InvalidFlags::TooLong);
+            }
+            let (last, rest) = bytes.split_last().ok_or(InvalidFlags::Truncated)?;
+            if last & Self::CONTINUATION != 0 {
+                return Err(InvalidFlags::Truncated);
+            }
+            if rest.iter().any(|byte| byte & Self::CONTINUATION == 0) {
+                return Err(InvalidFlags::NonCanonical);
+            }
+            // a continuation into an empty byte could have been left off
+            if !rest.is_empty() && *last == 0 {
+                return Err(InvalidFlags::NonCanonical);
+            }
+
+            let bits = bytes.iter().enumerate().fold(0u64, |bits, (index, byte)| {
+                bits | u64::from(byte & !Self::CONTINUATION) << (7 * index)
+            });
+            // bits past a u16 are never known
+            let unknown = bits & !u64::from(known.bits());
+            match u16::try_from(bits) {
+                Ok(bits) if unknown == 0 => Ok(Self::from_bits_retain(bits)),
+                _ => Err(InvalidFlags::UnknownBits(unknown)),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum InvalidFlags {
+        /// The last flags byte has a continuation, or there are none.
+        Truncated,
+        /// The flags could have been encoded in fewer bytes.
+        NonCanonical,
+        /// More flags bytes than [`ComputeBudgetFlags::MAX_SERIALIZED_SIZE`].
+        TooLong,
+        /// Bits, in flag order, that don't correspond to any known field.
+        UnknownBits(u64),
+    }
+
+    impl std::error::Error for InvalidFlags {}
+
+    impl core::fmt::Display for InvalidFlags {
+        fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            match self {
+                InvalidFlags::Truncated => f.write_str("truncated ComputeBudgetFlags"),
+                InvalidFlags::NonCanonical => f.write_str("non-canonical ComputeBudgetFlags"),
+                InvalidFlags::TooLong => f.write_str("too many ComputeBudgetFlags bytes"),
+                InvalidFlags::UnknownBits(bits) => {
+                    write!(f, "invalid ComputeBudgetFlags bits: {bits:#010b}")
+                }
+            }
+        }
+    }
+
+    /// [`ComputeBudgetFlags`] in their wire encoding, see
+    /// [`ComputeBudgetFlags::to_bytes`].
+    #[cfg(feature = "serde")]
+    pub(crate) struct WireFlags(pub(crate) ComputeBudgetFlags);
+
+    #[cfg(feature = "serde")]
+    impl serde::Serialize for WireFlags {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            use serde::ser::SerializeTuple;
+
+            let bytes = self.0.to_bytes();
+            let mut seq = serializer.serialize_tuple(bytes.len())?;
+            for byte in &bytes {
+                seq.serialize_element(byte)?;
+            }
+            seq.end()
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de> serde::Deserialize<'de> for WireFlags {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            use core::fmt;
+
+            use serde::de::{Error, SeqAccess, Visitor};
+
+            struct WireFlagsVisitor;
+
+            impl<'de> Visitor<'de> for WireFlagsVisitor {
+                type Value = WireFlags;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("ComputeBudgetFlags bytes")
+                }
+
+                fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<WireFlags, A::Error> {
+                    let mut bytes = Vec::with_capacity(ComputeBudgetFlags::MAX_SERIALIZED_SIZE);
+                    while bytes.len() < ComputeBudgetFlags::MAX_SERIALIZED_SIZE {
+                        let byte: u8 = seq
+                            .next_element()?
+                            .ok_or_else(|| Error::invalid_length(bytes.len(), &self))?;
+                        bytes.push(byte);
+                        if byte & ComputeBudgetFlags::CONTINUATION == 0 {
+                            break;
+                        }
+                    }
+                    ComputeBudgetFlags::from_bytes(&bytes)
+                        .map(WireFlags)
+                        .map_err(Error::custom)
+                }
+            }
+
+            deserializer.deserialize_tuple(ComputeBudgetFlags::MAX_SERIALIZED_SIZE, WireFlagsVisitor)
         }
     }
 
@@ -199,8 +762,8 @@ pub mod v3 {
 
 
     #[cfg(feature = "serde")]
-    mod compute_budget_header_serde {
-        use crate::v3::ComputeBudgetFlags;
+    pub(crate) mod compute_budget_header_serde {
+        use crate::v3::{ComputeBudgetFlags, WireFlags};
 
         use super::ComputeBudgetHeader;
         use serde::{de::SeqAccess, ser::SerializeStruct, Deserializer, Serializer};
@@ -217,14 +780,15 @@ pub mod v3 {
                 compute_unit_limit,
                 compute_unit_price,
                 loaded_accounts_data_limit,
-                requested_heap_bytes_limit
+                requested_heap_bytes_limit,
+                max_fee_lamports,
             } = value;
 
             let num_present_flags = flags.iter().count();
             let mut serde_state = serializer.serialize_struct("ComputeBudgetHeader", 1 + num_present_flags)? ;
 
 
-            serde_state.serialize_field("flags", &value.flags)?;
+            serde_state.serialize_field("flags", &WireFlags(*flags))?;
             if let Some(compute_unit_limit)= compute_unit_limit {
                 serde_state.serialize_field("compute_unit_limit", &compute_unit_limit)? ;
             }
@@ -237,6 +801,9 @@ pub mod v3 {
             if let Some(requested_heap_bytes_limit)= requested_heap_bytes_limit {
                 serde_state.serialize_field("requested_heap_bytes_limit", &requested_heap_bytes_limit)? ;
             }
+            if let Some(max_fee_lamports)= max_fee_lamports {
+                serde_state.serialize_field("max_fee_lamports", &max_fee_lamports)? ;
+            }
 
             serde_state.end()
         }
@@ -257,6 +824,7 @@ pub mod v3 {
                 ComputeUnitPrice,
                 LoadedAccountsDataSize,
                 RequestedHeapBytesLimit,
+                MaxFeeLamports,
             }
         
             struct ComputeBudgetHeaderVisitor;
@@ -277,6 +845,7 @@ pub mod v3 {
                     let mut compute_unit_price: Option<u64> = None;
                     let mut loaded_accounts_data_limit: Option<u32> = None;
                     let mut requested_heap_bytes_limit: Option<u32> = None;
+                    let mut max_fee_lamports: Option<u64> = None;
         
                     while let Some(key) = map.next_key()? {
                         match key {
@@ -284,7 +853,7 @@ pub mod v3 {
                                 if flags.is_some() {
                                     return Err(Error::duplicate_field("flags"));
                                 }
-                                flags = Some(map.next_value()?);
+                                flags = Some(map.next_value::<WireFlags>()?.0);
                             }
                             Field::ComputeUnitLimit => {
                                 if compute_unit_limit.is_some() {
@@ -310,19 +879,18 @@ pub mod v3 {
                                 }
                                 requested_heap_bytes_limit = Some(map.next_value()?);
                             }
+                            Field::MaxFeeLamports => {
+                                if max_fee_lamports.is_some() {
+                                    return Err(Error::duplicate_field("max_fee_lamports"));
+                                }
+                                max_fee_lamports = Some(map.next_value()?);
+                            }
                         }
                     }
         
+                    // unknown bits were already rejected when decoding the flags
                     let flags = flags.ok_or_else(|| Error::missing_field("flags"))?;
 
-                    // reject invalid bits set in ComputeBudgetFlags::all()
-                    let invalid_bits = flags.bits() & !ComputeBudgetFlags::all().bits();
-                    if invalid_bits != 0 {
-                        return Err(Error::custom(format!(
-                            "invalid ComputeBudgetFlags bits: {:#08b}", invalid_bits
-                        )));
-                    }
-
 
         
                     Ok(ComputeBudgetHeader {
@@ -331,6 +899,7 @@ pub mod v3 {
                         compute_unit_price,
                         loaded_accounts_data_limit,
                         requested_heap_bytes_limit,
+                        max_fee_lamports,
                     })
                 }
 
@@ -338,17 +907,10 @@ pub mod v3 {
                 where
                     A: SeqAccess<'de>,
                 {
-                    // flags is always present
-                    let flags: ComputeBudgetFlags = seq
+                    // flags is always present, and rejects unknown bits itself
+                    let WireFlags(flags) = seq
                         .next_element()?
                         .ok_or_else(|| Error::invalid_length(0, &self))?;
-                    let invalid = flags.bits() & !ComputeBudgetFlags::all().bits();
-                    if invalid != 0 {
-                        return Err(Error::custom(format!(
-                            "invalid ComputeBudgetFlags bits: {:#08b}",
-                            invalid
-                        )));
-                    }
 
                     // only read each option if the corresponding flag bit is set
                     let compute_unit_limit = if flags.contains(ComputeBudgetFlags::COMPUTE_UNIT_LIMIT) {
@@ -377,6 +939,12 @@ pub mod v3 {
                             None
                         };
 
+                    let max_fee_lamports = if flags.contains(ComputeBudgetFlags::MAX_FEE_LAMPORTS) {
+                        Some(seq.next_element()?.ok_or_else(|| Error::invalid_length(5, &self))?)
+                    } else {
+                        None
+                    };
+
 
                     Ok(ComputeBudgetHeader {
                         flags,
@@ -384,6 +952,7 @@ pub mod v3 {
                         compute_unit_price,
                         loaded_accounts_data_limit,
                         requested_heap_bytes_limit,
+                        max_fee_lamports,
                     })
                 }
             }
@@ -394,6 +963,7 @@ pub mod v3 {
                 "compute_unit_price",
                 "loaded_accounts_data_limit",
                 "requested_heap_bytes_limit",
+                "max_fee_lamports",
             ];
             deserializer.deserialize_struct(
                 "ComputeBudgetHeader",
@@ -409,49 +979,1068 @@ pub mod v3 {
         let cu_limits = [Some(34), None];
         let loaded_data_limits = [Some(56), None];
         let heap_limits = [Some(78), None];
+        let max_fees = [Some(90), None];
 
         for cu_price in cu_prices {
             for cu_limit in cu_limits {
                 for loaded_data_limit in loaded_data_limits {
                     for heap_limit in heap_limits {
-                        let header = ComputeBudgetHeader::new(cu_limit, cu_price, loaded_data_limit, heap_limit);
-                        let result = bincode::deserialize::<ComputeBudgetHeader>(&bincode::serialize(&header).unwrap()).unwrap();
-                        assert_eq!(header, result)
+                        for max_fee in max_fees {
+                            let header = ComputeBudgetHeader::new(cu_limit, cu_price, loaded_data_limit, heap_limit)
+                                .with_max_fee_lamports(max_fee);
+                            let result = bincode::deserialize::<ComputeBudgetHeader>(&bincode::serialize(&header).unwrap()).unwrap();
+                            assert_eq!(header, result)
+                        }
                     }
                 }
             }
         }
     }
 
+    #[test]
+    fn test_flags_continuation() {
+        // the fields defined so far fit in one byte, as they always have
+        let flags = ComputeBudgetFlags::all();
+        assert_eq!(flags.to_bytes(), [0b00011111]);
+        assert_eq!(ComputeBudgetFlags::from_bytes(&[0b00011111]), Ok(flags));
+        assert_eq!(ComputeBudgetFlags::empty().to_bytes(), [0]);
+
+        // a field past the first 7 adds a second byte, and decodes once known
+        let extended = ComputeBudgetFlags::from_bits_retain(1 << 7) | ComputeBudgetFlags::COMPUTE_UNIT_LIMIT;
+        let known = ComputeBudgetFlags::all() | extended;
+        assert_eq!(extended.to_bytes(), [0b10000001, 0b00000001]);
+        assert_eq!(extended.serialized_size(), 2);
+        assert_eq!(ComputeBudgetFlags::from_bytes_with_known(&extended.to_bytes(), known), Ok(extended));
+        assert_eq!(
+            ComputeBudgetFlags::from_bytes(&extended.to_bytes()),
+            Err(InvalidFlags::UnknownBits(1 << 7))
+        );
+        let all_bits = ComputeBudgetFlags::from_bits_retain(u16::MAX);
+        assert_eq!(all_bits.to_bytes(), [0xff, 0xff, 0b00000011]);
+        assert_eq!(ComputeBudgetFlags::from_bytes_with_known(&all_bits.to_bytes(), all_bits), Ok(all_bits));
+        assert_eq!(
+            ComputeBudgetFlags::from_bytes_with_known(&[0x80, 0x80, 0b00000100], all_bits),
+            Err(InvalidFlags::UnknownBits(1 << 16))
+        );
+
+        // only the shortest, complete encoding decodes
+        assert_eq!(ComputeBudgetFlags::from_bytes(&[0b10000001, 0]), Err(InvalidFlags::NonCanonical));
+        assert_eq!(ComputeBudgetFlags::from_bytes(&[0b00000001, 0]), Err(InvalidFlags::NonCanonical));
+        assert_eq!(ComputeBudgetFlags::from_bytes(&[0b10000001]), Err(InvalidFlags::Truncated));
+        assert_eq!(ComputeBudgetFlags::from_bytes(&[]), Err(InvalidFlags::Truncated));
+
+        // nor one longer than a u16 of flags needs, however the shift would wrap
+        let mut long = [0x80; 11];
+        long[10] = 0b00000001;
+        assert_eq!(ComputeBudgetFlags::from_bytes(&long), Err(InvalidFlags::TooLong));
+        let mut long = [0x80; 65];
+        long[64] = 0b00000001;
+        assert_eq!(ComputeBudgetFlags::from_bytes(&long), Err(InvalidFlags::TooLong));
+        assert_eq!(
+            ComputeBudgetFlags::from_bytes_with_known(&[0x80, 0x80, 0x80, 0b00000001], all_bits),
+            Err(InvalidFlags::TooLong)
+        );
+
+        // and a message with an unknown extended field doesn't decode
+        let message = Message {
+            compute_budget_header: ComputeBudgetHeader::new(Some(1), None, None, None),
+            header: MessageHeader::default(),
+            account_keys: vec![],
+            recent_blockhash: Hash::default(),
+            instructions: vec![],
+            address_table_lookups: vec![],
+        };
+        let bytes = bincode::serialize(&message).unwrap();
+        assert_eq!(bincode::deserialize::<Message>(&bytes).unwrap(), message);
+        let mut extended_bytes = vec![0b10000001, 0b00000001];
+        extended_bytes.extend_from_slice(&bytes[1..]);
+        assert!(bincode::deserialize::<Message>(&extended_bytes).is_err());
+    }
+
     #[test]
     fn test_roundtrip_message_all_bincode() {
         let cu_prices = [Some(12), None];
         let cu_limits = [Some(34), None];
         let loaded_data_limits = [Some(56), None];
         let heap_limits = [Some(78), None];
+        let max_fees = [Some(90), None];
 
         for cu_price in cu_prices {
             for cu_limit in cu_limits {
                 for loaded_data_limit in loaded_data_limits {
                     for heap_limit in heap_limits {
-                        let message = Message {
-                            compute_budget_header: ComputeBudgetHeader::new(cu_limit, cu_price, loaded_data_limit, heap_limit),
-                            header: MessageHeader {
-                                num_required_signatures: 1,
-                                num_readonly_signed_accounts: 2,
-                                num_readonly_unsigned_accounts: 3,
-                            },
-                            account_keys: vec![],
-                            recent_blockhash: Hash::new_unique(),
-                            instructions: vec![],
-                            address_table_lookups: vec![],
-                            
-                        };
-                        let result = bincode::deserialize::<Message>(&bincode::serialize(&message).unwrap()).unwrap();
-                        assert_eq!(message, result)
+                        for max_fee in max_fees {
+                            let message = Message {
+                                compute_budget_header: ComputeBudgetHeader::new(cu_limit, cu_price, loaded_data_limit, heap_limit)
+                                    .with_max_fee_lamports(max_fee),
+                                header: MessageHeader {
+                                    num_required_signatures: 1,
+                                    num_readonly_signed_accounts: 2,
+                                    num_readonly_unsigned_accounts: 3,
+                                },
+                                account_keys: vec![],
+                                recent_blockhash: Hash::new_unique(),
+                                instructions: vec![],
+                                address_table_lookups: vec![],
+                            };
+                            let bytes = bincode::serialize(&message).unwrap();
+                            assert_eq!(bytes.len(), message.compute_budget_header.serialized_size() + 3 + 1 + 32 + 1 + 1);
+                            let result = bincode::deserialize::<Message>(&bytes).unwrap();
+                            assert_eq!(message, result)
+                        }
                     }
                 }
             }
         }
     }
 }
+
+// v3's ComputeBudgetFlags, with each present field as a LEB128 varint
+pub mod v4 {
+    use super::*;
+
+    use solana_message::MessageHeader;
+
+    pub use crate::v3::ComputeBudgetFlags;
+
+    #[cfg_attr(
+        feature = "serde",
+        derive(Deserialize, Serialize),
+        serde(rename_all = "camelCase")
+    )]
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Message {
+        #[cfg_attr(feature = "serde", serde(with = "compute_budget_header_serde"))]
+        pub compute_budget_header: ComputeBudgetHeader,
+
+        pub header: MessageHeader,
+
+        #[cfg_attr(feature = "serde", serde(with = "solana_short_vec"))]
+        pub account_keys: Vec<Pubkey>,
+
+        pub recent_blockhash: Hash,
+
+        #[cfg_attr(feature = "serde", serde(with = "solana_short_vec"))]
+        pub instructions: Vec<CompiledInstruction>,
+
+        #[cfg_attr(feature = "serde", serde(with = "solana_short_vec"))]
+        pub address_table_lookups: Vec<MessageAddressTableLookup>,
+    }
+
+    impl Message {
+        /// Sanitize message fields and compiled instruction indexes
+        pub fn sanitize(&self) -> Result<(), SanitizeError> {
+            sanitize_message(
+                &self.header,
+                &self.account_keys,
+                &self.instructions,
+                &self.address_table_lookups,
+            )
+        }
+    }
+
+    /// The same fields as [`crate::v3::ComputeBudgetHeader`], encoded as the
+    /// flags byte followed by a varint for each present field.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ComputeBudgetHeader {
+        flags: ComputeBudgetFlags,
+        compute_unit_limit: Option<u32>,
+        compute_unit_price: Option<u64>,
+        loaded_accounts_data_limit: Option<u32>,
+        requested_heap_bytes_limit: Option<u32>,
+        max_fee_lamports: Option<u64>,
+    }
+
+    impl ComputeBudgetHeader {
+        pub fn new(
+            compute_unit_limit: Option<u32>,
+            compute_unit_price: Option<u64>,
+            loaded_accounts_data_limit: Option<u32>,
+            requested_heap_bytes_limit: Option<u32>,
+        ) -> ComputeBudgetHeader {
+            let mut flags = ComputeBudgetFlags::empty();
+            flags.set(
+                ComputeBudgetFlags::COMPUTE_UNIT_LIMIT,
+                compute_unit_limit.is_some(),
+            );
+            flags.set(
+                ComputeBudgetFlags::COMPUTE_UNIT_PRICE,
+                compute_unit_price.is_some(),
+            );
+            flags.set(
+                ComputeBudgetFlags::LOADED_ACCOUNTS_DATA_LIMIT,
+                loaded_accounts_data_limit.is_some(),
+            );
+            flags.set(
+                ComputeBudgetFlags::REQUESTED_HEAP_BYTES_LIMIT,
+                requested_heap_bytes_limit.is_some(),
+            );
+
+            ComputeBudgetHeader {
+                flags,
+                compute_unit_limit,
+                compute_unit_price,
+                loaded_accounts_data_limit,
+                requested_heap_bytes_limit,
+                max_fee_lamports: None,
+            }
+        }
+
+        /// Cap the total fee the message can be charged, see
+        /// [`crate::fee::FeeDetails`].
+        pub fn with_max_fee_lamports(
+            mut self,
+            max_fee_lamports: Option<u64>,
+        ) -> ComputeBudgetHeader {
+            self.flags.set(
+                ComputeBudgetFlags::MAX_FEE_LAMPORTS,
+                max_fee_lamports.is_some(),
+            );
+            self.max_fee_lamports = max_fee_lamports;
+            self
+        }
+
+        pub fn flags(&self) -> ComputeBudgetFlags {
+            self.flags
+        }
+
+        pub fn compute_unit_limit(&self) -> Option<u32> {
+            self.compute_unit_limit
+        }
+
+        pub fn compute_unit_price(&self) -> Option<u64> {
+            self.compute_unit_price
+        }
+
+        pub fn loaded_accounts_data_limit(&self) -> Option<u32> {
+            self.loaded_accounts_data_limit
+        }
+
+        pub fn requested_heap_bytes_limit(&self) -> Option<u32> {
+            self.requested_heap_bytes_limit
+        }
+
+        pub fn max_fee_lamports(&self) -> Option<u64> {
+            self.max_fee_lamports
+        }
+
+        /// Number of bytes this header occupies on the wire: the flags bytes
+        /// followed by the varint of each present field.
+        pub fn serialized_size(&self) -> usize {
+            self.flags.serialized_size()
+                + self.compute_unit_limit.map_or(0, |v| varint_len(v.into()))
+                + self.compute_unit_price.map_or(0, varint_len)
+                + self
+                    .loaded_accounts_data_limit
+                    .map_or(0, |v| varint_len(v.into()))
+                + self
+                    .requested_heap_bytes_limit
+                    .map_or(0, |v| varint_len(v.into()))
+                + self.max_fee_lamports.map_or(0, varint_len)
+        }
+    }
+
+    /// Longest LEB128 encoding of a `u64`.
+    pub const MAX_VARINT_LEN: usize = 10;
+
+    /// Number of bytes in the LEB128 encoding of `value`: one per started
+    /// group of 7 bits.
+    pub const fn varint_len(value: u64) -> usize {
+        let bits = u64::BITS - value.leading_zeros();
+        if bits == 0 {
+            1
+        } else {
+            bits.div_ceil(7) as usize
+        }
+    }
+
+    /// A `u64` encoded as LEB128: 7 bits per byte, least significant group
+    /// first, with the high bit set on every byte but the last. Only the
+    /// shortest encoding of a value decodes.
+    #[cfg(feature = "serde")]
+    struct VarInt(u64);
+
+    #[cfg(feature = "serde")]
+    impl serde::Serialize for VarInt {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            use serde::ser::SerializeTuple;
+
+            let len = varint_len(self.0);
+            let mut seq = serializer.serialize_tuple(len)?;
+            let mut rem = self.0;
+            for _ in 1..len {
+                seq.serialize_element(&((rem as u8 & 0x7f) | 0x80))?;
+                rem >>= 7;
+            }
+            seq.serialize_element(&(rem as u8))?;
+            seq.end()
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de> serde::Deserialize<'de> for VarInt {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            use core::fmt;
+
+            use serde::de::{Error, SeqAccess, Visitor};
+
+            struct VarIntVisitor;
+
+            impl<'de> Visitor<'de> for VarIntVisitor {
+                type Value = VarInt;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("a LEB128 varint")
+                }
+
+                fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<VarInt, A::Error> {
+                    let mut value = 0u64;
+                    for index in 0..MAX_VARINT_LEN {
+                        let byte: u8 = seq
+                            .next_element()?
+                            .ok_or_else(|| Error::invalid_length(index, &self))?;
+                        // the last byte only has room for the top bit of a u64
+                        if index == MAX_VARINT_LEN - 1 && byte > 1 {
+                            return Err(Error::custom("varint overflows u64"));
+                        }
+                        value |= u64::from(byte & 0x7f) << (7 * index);
+                        if byte & 0x80 == 0 {
+                            if index > 0 && byte == 0 {
+                                return Err(Error::custom("non-canonical varint"));
+                            }
+                            return Ok(VarInt(value));
+                        }
+                    }
+                    Err(Error::custom("varint overflows u64"))
+                }
+            }
+
+            deserializer.deserialize_tuple(MAX_VARINT_LEN, VarIntVisitor)
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    mod compute_budget_header_serde {
+        use core::fmt;
+
+        use serde::{
+            Deserializer, Serializer,
+            de::{Error, SeqAccess, Visitor},
+            ser::SerializeTuple,
+        };
+
+        use super::{ComputeBudgetFlags, ComputeBudgetHeader, VarInt};
+        use crate::v3::WireFlags;
+
+        pub fn serialize<S>(value: &ComputeBudgetHeader, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let fields = [
+                value.compute_unit_limit.map(u64::from),
+                value.compute_unit_price,
+                value.loaded_accounts_data_limit.map(u64::from),
+                value.requested_heap_bytes_limit.map(u64::from),
+                value.max_fee_lamports,
+            ];
+
+            let mut seq = serializer.serialize_tuple(1 + value.flags.iter().count())?;
+            seq.serialize_element(&WireFlags(value.flags))?;
+            for field in fields.into_iter().flatten() {
+                seq.serialize_element(&VarInt(field))?;
+            }
+            seq.end()
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<ComputeBudgetHeader, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct ComputeBudgetHeaderVisitor;
+
+            impl<'de> Visitor<'de> for ComputeBudgetHeaderVisitor {
+                type Value = ComputeBudgetHeader;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("struct ComputeBudgetHeader")
+                }
+
+                fn visit_seq<A>(self, mut seq: A) -> Result<ComputeBudgetHeader, A::Error>
+                where
+                    A: SeqAccess<'de>,
+                {
+                    // flags is always present, and rejects unknown bits itself
+                    let WireFlags(flags) = seq
+                        .next_element()?
+                        .ok_or_else(|| Error::invalid_length(0, &self))?;
+
+                    // only read each varint if the corresponding flag bit is set
+                    let mut index = 0;
+                    let mut next = |flag: ComputeBudgetFlags| -> Result<Option<u64>, A::Error> {
+                        if !flags.contains(flag) {
+                            return Ok(None);
+                        }
+                        index += 1;
+                        let VarInt(value) = seq
+                            .next_element()?
+                            .ok_or_else(|| Error::invalid_length(index, &self))?;
+                        Ok(Some(value))
+                    };
+                    let compute_unit_limit = next(ComputeBudgetFlags::COMPUTE_UNIT_LIMIT)?;
+                    let compute_unit_price = next(ComputeBudgetFlags::COMPUTE_UNIT_PRICE)?;
+                    let loaded_accounts_data_limit =
+                        next(ComputeBudgetFlags::LOADED_ACCOUNTS_DATA_LIMIT)?;
+                    let requested_heap_bytes_limit =
+                        next(ComputeBudgetFlags::REQUESTED_HEAP_BYTES_LIMIT)?;
+                    let max_fee_lamports = next(ComputeBudgetFlags::MAX_FEE_LAMPORTS)?;
+
+                    let to_u32 = |value: Option<u64>| {
+                        value
+                            .map(u32::try_from)
+                            .transpose()
+                            .map_err(|_| Error::custom("varint overflows u32"))
+                    };
+                    Ok(ComputeBudgetHeader {
+                        flags,
+                        compute_unit_limit: to_u32(compute_unit_limit)?,
+                        compute_unit_price,
+                        loaded_accounts_data_limit: to_u32(loaded_accounts_data_limit)?,
+                        requested_heap_bytes_limit: to_u32(requested_heap_bytes_limit)?,
+                        max_fee_lamports,
+                    })
+                }
+            }
+
+            deserializer.deserialize_tuple(6, ComputeBudgetHeaderVisitor)
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_roundtrip_header_all_bincode() {
+        let values = [0, 1, 127, 128, 12345, u64::from(u32::MAX), u64::MAX];
+        for value in values {
+            let limit = u32::try_from(value).ok();
+            let header = ComputeBudgetHeader::new(limit, Some(value), None, limit);
+            let wrapper = Message {
+                compute_budget_header: header,
+                header: MessageHeader::default(),
+                account_keys: vec![],
+                recent_blockhash: Hash::default(),
+                instructions: vec![],
+                address_table_lookups: vec![],
+            };
+            let bytes = bincode::serialize(&wrapper).unwrap();
+            assert_eq!(bytes.len(), header.serialized_size() + 3 + 1 + 32 + 1 + 1);
+            assert_eq!(
+                bincode::deserialize::<Message>(&bytes)
+                    .unwrap()
+                    .compute_budget_header,
+                header
+            );
+        }
+
+        // the fee cap is a varint too
+        let header =
+            ComputeBudgetHeader::new(None, None, None, None).with_max_fee_lamports(Some(u64::MAX));
+        assert_eq!(header.serialized_size(), 1 + MAX_VARINT_LEN);
+        let wrapper = Message {
+            compute_budget_header: header,
+            header: MessageHeader::default(),
+            account_keys: vec![],
+            recent_blockhash: Hash::default(),
+            instructions: vec![],
+            address_table_lookups: vec![],
+        };
+        let bytes = bincode::serialize(&wrapper).unwrap();
+        assert_eq!(bincode::deserialize::<Message>(&bytes).unwrap(), wrapper);
+
+        // a typical price takes 2 bytes instead of 8
+        let header = ComputeBudgetHeader::new(None, Some(12345), None, None);
+        let bytes = bincode::serialize(&VarInt(12345)).unwrap();
+        assert_eq!(bytes, [0xb9, 0x60]);
+        assert_eq!(header.serialized_size(), 3);
+
+        // only the shortest encoding of a value decodes
+        let decode = |bytes: &[u8]| bincode::deserialize::<VarInt>(bytes).map(|v| v.0);
+        assert_eq!(decode(&[0x80, 0x01]).unwrap(), 128);
+        assert!(decode(&[0x80, 0x00]).is_err());
+        assert!(decode(&[0x80]).is_err());
+        assert!(decode(&[0xff; 9].iter().copied().chain([0x02]).collect::<Vec<_>>()).is_err());
+        assert_eq!(
+            decode(&[0xff; 9].iter().copied().chain([0x01]).collect::<Vec<_>>()).unwrap(),
+            u64::MAX
+        );
+
+        // u32 fields reject values that don't fit
+        let mut bytes =
+            bincode::serialize(&(ComputeBudgetFlags::COMPUTE_UNIT_LIMIT, VarInt(1 << 32))).unwrap();
+        bytes.extend_from_slice(&bincode::serialize(&MessageHeader::default()).unwrap());
+        bytes.extend_from_slice(&[0; 32 + 3]);
+        assert!(bincode::deserialize::<Message>(&bytes).is_err());
+        bytes[1..6].copy_from_slice(&[0xff, 0xff, 0xff, 0xff, 0x0f]);
+        assert!(bincode::deserialize::<Message>(&bytes).is_ok());
+
+        // and unknown flag bits are rejected, as in v3
+        bytes[0] = 0b10_0000;
+        assert!(bincode::deserialize::<Message>(&bytes).is_err());
+    }
+}
+
+// v3 with the compute unit price as a mantissa and a power of ten
+pub mod v5 {
+    use super::*;
+
+    use core::fmt;
+
+    use solana_message::MessageHeader;
+
+    pub use crate::v3::ComputeBudgetFlags;
+
+    #[cfg_attr(
+        feature = "serde",
+        derive(Deserialize, Serialize),
+        serde(rename_all = "camelCase")
+    )]
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Message {
+        #[cfg_attr(feature = "serde", serde(with = "compute_budget_header_serde"))]
+        pub compute_budget_header: ComputeBudgetHeader,
+
+        pub header: MessageHeader,
+
+        #[cfg_attr(feature = "serde", serde(with = "solana_short_vec"))]
+        pub account_keys: Vec<Pubkey>,
+
+        pub recent_blockhash: Hash,
+
+        #[cfg_attr(feature = "serde", serde(with = "solana_short_vec"))]
+        pub instructions: Vec<CompiledInstruction>,
+
+        #[cfg_attr(feature = "serde", serde(with = "solana_short_vec"))]
+        pub address_table_lookups: Vec<MessageAddressTableLookup>,
+    }
+
+    impl Message {
+        /// Sanitize message fields and compiled instruction indexes
+        pub fn sanitize(&self) -> Result<(), SanitizeError> {
+            sanitize_message(
+                &self.header,
+                &self.account_keys,
+                &self.instructions,
+                &self.address_table_lookups,
+            )
+        }
+    }
+
+    /// A compute unit price of `mantissa * 10^exponent` micro-lamports,
+    /// encoded as the exponent byte followed by the `u32` mantissa: 5 bytes
+    /// instead of 8 for any price whose mantissa after removing trailing zeros
+    /// fits in a `u32` (every price with at most 9 significant digits).
+    ///
+    /// Trailing zeros always go in the exponent, so each price has exactly one
+    /// encoding.
+    #[cfg_attr(
+        feature = "serde",
+        derive(Deserialize, Serialize),
+        serde(try_from = "(u8, u32)", into = "(u8, u32)")
+    )]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct ScaledPrice {
+        exponent: u8,
+        mantissa: u32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ScaledPriceError {
+        /// The price's mantissa after removing trailing zeros doesn't fit in a
+        /// `u32`.
+        Lossy,
+        /// The mantissa has trailing zeros that belong in the exponent.
+        NonCanonical,
+        /// The scaled price doesn't fit in a `u64`.
+        Overflow,
+    }
+
+    impl std::error::Error for ScaledPriceError {}
+
+    impl fmt::Display for ScaledPriceError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                ScaledPriceError::Lossy => {
+                    f.write_str("price can't be scaled without losing digits")
+                }
+                ScaledPriceError::NonCanonical => f.write_str("non-canonical scaled price"),
+                ScaledPriceError::Overflow => f.write_str("scaled price overflows u64"),
+            }
+        }
+    }
+
+    impl ScaledPrice {
+        pub fn new(exponent: u8, mantissa: u32) -> Result<Self, ScaledPriceError> {
+            if mantissa.is_multiple_of(10) && (mantissa != 0 || exponent != 0) {
+                return Err(ScaledPriceError::NonCanonical);
+            }
+            10u64
+                .checked_pow(u32::from(exponent))
+                .and_then(|scale| scale.checked_mul(u64::from(mantissa)))
+                .ok_or(ScaledPriceError::Overflow)?;
+            Ok(Self { exponent, mantissa })
+        }
+
+        pub fn exponent(&self) -> u8 {
+            self.exponent
+        }
+
+        pub fn mantissa(&self) -> u32 {
+            self.mantissa
+        }
+
+        /// The price in micro-lamports per compute unit.
+        pub fn value(&self) -> u64 {
+            u64::from(self.mantissa) * 10u64.pow(u32::from(self.exponent))
+        }
+    }
+
+    impl TryFrom<u64> for ScaledPrice {
+        type Error = ScaledPriceError;
+
+        fn try_from(mut value: u64) -> Result<Self, Self::Error> {
+            let mut exponent = 0;
+            while value != 0 && value.is_multiple_of(10) {
+                value /= 10;
+                exponent += 1;
+            }
+            let mantissa = u32::try_from(value).map_err(|_| ScaledPriceError::Lossy)?;
+            Ok(Self { exponent, mantissa })
+        }
+    }
+
+    impl TryFrom<(u8, u32)> for ScaledPrice {
+        type Error = ScaledPriceError;
+
+        fn try_from((exponent, mantissa): (u8, u32)) -> Result<Self, Self::Error> {
+            Self::new(exponent, mantissa)
+        }
+    }
+
+    impl From<ScaledPrice> for (u8, u32) {
+        fn from(price: ScaledPrice) -> Self {
+            (price.exponent, price.mantissa)
+        }
+    }
+
+    /// The same fields as [`crate::v3::ComputeBudgetHeader`], with the price
+    /// as a [`ScaledPrice`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ComputeBudgetHeader {
+        flags: ComputeBudgetFlags,
+        compute_unit_limit: Option<u32>,
+        compute_unit_price: Option<ScaledPrice>,
+        loaded_accounts_data_limit: Option<u32>,
+        requested_heap_bytes_limit: Option<u32>,
+        max_fee_lamports: Option<u64>,
+    }
+
+    impl ComputeBudgetHeader {
+        pub fn new(
+            compute_unit_limit: Option<u32>,
+            compute_unit_price: Option<ScaledPrice>,
+            loaded_accounts_data_limit: Option<u32>,
+            requested_heap_bytes_limit: Option<u32>,
+        ) -> ComputeBudgetHeader {
+            let mut flags = ComputeBudgetFlags::empty();
+            flags.set(
+                ComputeBudgetFlags::COMPUTE_UNIT_LIMIT,
+                compute_unit_limit.is_some(),
+            );
+            flags.set(
+                ComputeBudgetFlags::COMPUTE_UNIT_PRICE,
+                compute_unit_price.is_some(),
+            );
+            flags.set(
+                ComputeBudgetFlags::LOADED_ACCOUNTS_DATA_LIMIT,
+                loaded_accounts_data_limit.is_some(),
+            );
+            flags.set(
+                ComputeBudgetFlags::REQUESTED_HEAP_BYTES_LIMIT,
+                requested_heap_bytes_limit.is_some(),
+            );
+
+            ComputeBudgetHeader {
+                flags,
+                compute_unit_limit,
+                compute_unit_price,
+                loaded_accounts_data_limit,
+                requested_heap_bytes_limit,
+                max_fee_lamports: None,
+            }
+        }
+
+        /// Cap the total fee the message can be charged, see
+        /// [`crate::fee::FeeDetails`].
+        pub fn with_max_fee_lamports(
+            mut self,
+            max_fee_lamports: Option<u64>,
+        ) -> ComputeBudgetHeader {
+            self.flags.set(
+                ComputeBudgetFlags::MAX_FEE_LAMPORTS,
+                max_fee_lamports.is_some(),
+            );
+            self.max_fee_lamports = max_fee_lamports;
+            self
+        }
+
+        pub fn flags(&self) -> ComputeBudgetFlags {
+            self.flags
+        }
+
+        pub fn compute_unit_limit(&self) -> Option<u32> {
+            self.compute_unit_limit
+        }
+
+        /// The price in micro-lamports per compute unit.
+        pub fn compute_unit_price(&self) -> Option<u64> {
+            self.compute_unit_price.map(|price| price.value())
+        }
+
+        pub fn scaled_compute_unit_price(&self) -> Option<ScaledPrice> {
+            self.compute_unit_price
+        }
+
+        pub fn loaded_accounts_data_limit(&self) -> Option<u32> {
+            self.loaded_accounts_data_limit
+        }
+
+        pub fn requested_heap_bytes_limit(&self) -> Option<u32> {
+            self.requested_heap_bytes_limit
+        }
+
+        pub fn max_fee_lamports(&self) -> Option<u64> {
+            self.max_fee_lamports
+        }
+
+        /// Number of bytes this header occupies on the wire: the flags bytes
+        /// followed by each present field.
+        pub fn serialized_size(&self) -> usize {
+            self.flags.serialized_size()
+                + self.compute_unit_limit.map_or(0, |_| 4)
+                + self.compute_unit_price.map_or(0, |_| 5)
+                + self.loaded_accounts_data_limit.map_or(0, |_| 4)
+                + self.requested_heap_bytes_limit.map_or(0, |_| 4)
+                + self.max_fee_lamports.map_or(0, |_| 8)
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    mod compute_budget_header_serde {
+        use core::fmt;
+
+        use serde::{
+            Deserializer, Serializer,
+            de::{DeserializeOwned, Error, SeqAccess, Visitor},
+            ser::SerializeTuple,
+        };
+
+        use super::{ComputeBudgetFlags, ComputeBudgetHeader};
+        use crate::v3::WireFlags;
+
+        pub fn serialize<S>(value: &ComputeBudgetHeader, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let mut seq = serializer.serialize_tuple(1 + value.flags.iter().count())?;
+            seq.serialize_element(&WireFlags(value.flags))?;
+            if let Some(compute_unit_limit) = &value.compute_unit_limit {
+                seq.serialize_element(compute_unit_limit)?;
+            }
+            if let Some(compute_unit_price) = &value.compute_unit_price {
+                seq.serialize_element(compute_unit_price)?;
+            }
+            if let Some(loaded_accounts_data_limit) = &value.loaded_accounts_data_limit {
+                seq.serialize_element(loaded_accounts_data_limit)?;
+            }
+            if let Some(requested_heap_bytes_limit) = &value.requested_heap_bytes_limit {
+                seq.serialize_element(requested_heap_bytes_limit)?;
+            }
+            if let Some(max_fee_lamports) = &value.max_fee_lamports {
+                seq.serialize_element(max_fee_lamports)?;
+            }
+            seq.end()
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<ComputeBudgetHeader, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct ComputeBudgetHeaderVisitor;
+
+            impl<'de> Visitor<'de> for ComputeBudgetHeaderVisitor {
+                type Value = ComputeBudgetHeader;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("struct ComputeBudgetHeader")
+                }
+
+                fn visit_seq<A>(self, mut seq: A) -> Result<ComputeBudgetHeader, A::Error>
+                where
+                    A: SeqAccess<'de>,
+                {
+                    // flags is always present, and rejects unknown bits itself
+                    let WireFlags(flags) = seq
+                        .next_element()?
+                        .ok_or_else(|| Error::invalid_length(0, &self))?;
+
+                    // only read each field if the corresponding flag bit is set
+                    let mut index = 0;
+                    let compute_unit_limit = read_field(
+                        &mut seq,
+                        flags,
+                        ComputeBudgetFlags::COMPUTE_UNIT_LIMIT,
+                        &mut index,
+                        &self,
+                    )?;
+                    let compute_unit_price = read_field(
+                        &mut seq,
+                        flags,
+                        ComputeBudgetFlags::COMPUTE_UNIT_PRICE,
+                        &mut index,
+                        &self,
+                    )?;
+                    let loaded_accounts_data_limit = read_field(
+                        &mut seq,
+                        flags,
+                        ComputeBudgetFlags::LOADED_ACCOUNTS_DATA_LIMIT,
+                        &mut index,
+                        &self,
+                    )?;
+                    let requested_heap_bytes_limit = read_field(
+                        &mut seq,
+                        flags,
+                        ComputeBudgetFlags::REQUESTED_HEAP_BYTES_LIMIT,
+                        &mut index,
+                        &self,
+                    )?;
+                    let max_fee_lamports = read_field(
+                        &mut seq,
+                        flags,
+                        ComputeBudgetFlags::MAX_FEE_LAMPORTS,
+                        &mut index,
+                        &self,
+                    )?;
+                    Ok(ComputeBudgetHeader {
+                        flags,
+                        compute_unit_limit,
+                        compute_unit_price,
+                        loaded_accounts_data_limit,
+                        requested_heap_bytes_limit,
+                        max_fee_lamports,
+                    })
+                }
+            }
+
+            deserializer.deserialize_tuple(6, ComputeBudgetHeaderVisitor)
+        }
+
+        fn read_field<'de, A: SeqAccess<'de>, T: DeserializeOwned>(
+            seq: &mut A,
+            flags: ComputeBudgetFlags,
+            flag: ComputeBudgetFlags,
+            index: &mut usize,
+            visitor: &dyn serde::de::Expected,
+        ) -> Result<Option<T>, A::Error> {
+            if !flags.contains(flag) {
+                return Ok(None);
+            }
+            *index += 1;
+            seq.next_element()?
+                .map(Some)
+                .ok_or_else(|| Error::invalid_length(*index, visitor))
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_roundtrip_header_all_bincode() {
+        let prices = [
+            0,
+            1,
+            10,
+            12345,
+            1_000_000,
+            u64::from(u32::MAX),
+            10u64.pow(19),
+        ];
+        for value in prices {
+            let price = ScaledPrice::try_from(value).unwrap();
+            assert_eq!(price.value(), value);
+            let header = ComputeBudgetHeader::new(Some(1), Some(price), None, Some(2))
+                .with_max_fee_lamports(Some(value));
+            let wrapper = Message {
+                compute_budget_header: header,
+                header: MessageHeader::default(),
+                account_keys: vec![],
+                recent_blockhash: Hash::default(),
+                instructions: vec![],
+                address_table_lookups: vec![],
+            };
+            let bytes = bincode::serialize(&wrapper).unwrap();
+            assert_eq!(header.serialized_size(), 1 + 4 + 5 + 4 + 8);
+            assert_eq!(bytes.len(), header.serialized_size() + 3 + 1 + 32 + 1 + 1);
+            assert_eq!(
+                bincode::deserialize::<Message>(&bytes)
+                    .unwrap()
+                    .compute_budget_header,
+                header
+            );
+        }
+
+        // trailing zeros move to the exponent
+        let price = ScaledPrice::try_from(5_000_000).unwrap();
+        assert_eq!((price.exponent(), price.mantissa()), (6, 5));
+        assert_eq!(bincode::serialize(&price).unwrap(), [6, 5, 0, 0, 0]);
+
+        // prices whose mantissa doesn't fit in a u32 can't be scaled
+        assert_eq!(
+            ScaledPrice::try_from(u64::from(u32::MAX) + 2),
+            Err(ScaledPriceError::Lossy)
+        );
+
+        // only the canonical encoding of an in-range price decodes
+        assert_eq!(ScaledPrice::new(1, 50), Err(ScaledPriceError::NonCanonical));
+        assert_eq!(ScaledPrice::new(1, 0), Err(ScaledPriceError::NonCanonical));
+        assert_eq!(ScaledPrice::new(20, 1), Err(ScaledPriceError::Overflow));
+        assert_eq!(ScaledPrice::new(19, 2), Err(ScaledPriceError::Overflow));
+        let decode = |bytes: &[u8]| bincode::deserialize::<ScaledPrice>(bytes);
+        assert_eq!(decode(&[0, 0, 0, 0, 0]).unwrap().value(), 0);
+        assert!(decode(&[1, 50, 0, 0, 0]).is_err());
+        assert!(decode(&[255, 1, 0, 0, 0]).is_err());
+    }
+}
+
+// v3's ComputeBudgetFlags + dynamic payload, after the rest of the message
+#[cfg(feature = "v3b")]
+pub mod v3b {
+    use super::*;
+
+    use solana_message::MessageHeader;
+
+    pub use crate::v3::{ComputeBudgetFlags, ComputeBudgetHeader};
+
+    /// The fields of a [`crate::v3::Message`], with the compute budget header
+    /// serialized last instead of first. The legacy header and everything
+    /// after it sit at the same offsets as in v0, at the cost of walking the
+    /// whole message to find the budget.
+    #[cfg_attr(
+        feature = "serde",
+        derive(Deserialize, Serialize),
+        serde(rename_all = "camelCase")
+    )]
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Message {
+        pub header: MessageHeader,
+
+        #[cfg_attr(feature = "serde", serde(with = "solana_short_vec"))]
+        pub account_keys: Vec<Pubkey>,
+
+        pub recent_blockhash: Hash,
+
+        #[cfg_attr(feature = "serde", serde(with = "solana_short_vec"))]
+        pub instructions: Vec<CompiledInstruction>,
+
+        #[cfg_attr(feature = "serde", serde(with = "solana_short_vec"))]
+        pub address_table_lookups: Vec<MessageAddressTableLookup>,
+
+        #[cfg_attr(
+            feature = "serde",
+            serde(with = "crate::v3::compute_budget_header_serde")
+        )]
+        pub compute_budget_header: ComputeBudgetHeader,
+    }
+
+    impl Message {
+        /// Sanitize message fields and compiled instruction indexes
+        pub fn sanitize(&self) -> Result<(), SanitizeError> {
+            sanitize_message(
+                &self.header,
+                &self.account_keys,
+                &self.instructions,
+                &self.address_table_lookups,
+            )
+        }
+    }
+
+    impl From<crate::v3::Message> for Message {
+        fn from(message: crate::v3::Message) -> Self {
+            Message {
+                header: message.header,
+                account_keys: message.account_keys,
+                recent_blockhash: message.recent_blockhash,
+                instructions: message.instructions,
+                address_table_lookups: message.address_table_lookups,
+                compute_budget_header: message.compute_budget_header,
+            }
+        }
+    }
+
+    impl From<Message> for crate::v3::Message {
+        fn from(message: Message) -> Self {
+            crate::v3::Message {
+                compute_budget_header: message.compute_budget_header,
+                header: message.header,
+                account_keys: message.account_keys,
+                recent_blockhash: message.recent_blockhash,
+                instructions: message.instructions,
+                address_table_lookups: message.address_table_lookups,
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_roundtrip_message_all_bincode() {
+        let header = MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 2,
+            num_readonly_unsigned_accounts: 3,
+        };
+        let recent_blockhash = Hash::new_unique();
+        for bits in 0..=ComputeBudgetFlags::all().bits() {
+            let flag = |flag| ComputeBudgetFlags::from_bits_retain(bits).contains(flag);
+            let compute_budget_header = ComputeBudgetHeader::new(
+                flag(ComputeBudgetFlags::COMPUTE_UNIT_LIMIT).then_some(12),
+                flag(ComputeBudgetFlags::COMPUTE_UNIT_PRICE).then_some(34),
+                flag(ComputeBudgetFlags::LOADED_ACCOUNTS_DATA_LIMIT).then_some(56),
+                flag(ComputeBudgetFlags::REQUESTED_HEAP_BYTES_LIMIT).then_some(78),
+            )
+            .with_max_fee_lamports(flag(ComputeBudgetFlags::MAX_FEE_LAMPORTS).then_some(90));
+            let message = Message {
+                header,
+                account_keys: vec![],
+                recent_blockhash,
+                instructions: vec![],
+                address_table_lookups: vec![],
+                compute_budget_header,
+            };
+            let bytes = bincode::serialize(&message).unwrap();
+            assert_eq!(bincode::deserialize::<Message>(&bytes).unwrap(), message);
+
+            // the same bytes as v3, with the budget header moved to the end
+            let v3 = bincode::serialize(&crate::v3::Message::from(message)).unwrap();
+            let (budget, rest) = v3.split_at(compute_budget_header.serialized_size());
+            assert_eq!(bytes, [rest, budget].concat());
+        }
+
+        // a truncated budget header doesn't decode
+        let message = Message {
+            header,
+            account_keys: vec![],
+            recent_blockhash,
+            instructions: vec![],
+            address_table_lookups: vec![],
+            compute_budget_header: ComputeBudgetHeader::new(Some(12), None, None, None),
+        };
+        let bytes = bincode::serialize(&message).unwrap();
+        assert!(bincode::deserialize::<Message>(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
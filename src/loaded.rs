@@ -0,0 +1,385 @@
+//! Candidate messages joined with the addresses their lookup tables resolve
+//! to, the equivalent of `v0::LoadedMessage`.
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+use solana_message::{
+    AccountKeys, MessageHeader,
+    compiled_instruction::CompiledInstruction,
+    legacy,
+    v0::{self, LoadedAddresses, MessageAddressTableLookup},
+};
+use solana_pubkey::Pubkey;
+use solana_sdk_ids::bpf_loader_upgradeable;
+use solana_transaction_error::{AddressLoaderError, TransactionError};
+
+#[cfg(feature = "v3b")]
+use crate::v3b;
+use crate::{account_roles::AccountRoles, v1, v2, v3, v4, v5};
+
+/// The parts of a message needed to resolve and query its account keys.
+pub trait LoadableMessage: Clone {
+    fn header(&self) -> MessageHeader;
+    fn static_account_keys(&self) -> &[Pubkey];
+    fn instructions(&self) -> &[CompiledInstruction];
+    fn address_table_lookups(&self) -> &[MessageAddressTableLookup];
+}
+
+/// Legacy messages have no lookups, so loading them is a no-op.
+impl LoadableMessage for legacy::Message {
+    fn header(&self) -> MessageHeader {
+        self.header
+    }
+
+    fn static_account_keys(&self) -> &[Pubkey] {
+        &self.account_keys
+    }
+
+    fn instructions(&self) -> &[CompiledInstruction] {
+        &self.instructions
+    }
+
+    fn address_table_lookups(&self) -> &[MessageAddressTableLookup] {
+        &[]
+    }
+}
+
+impl LoadableMessage for v0::Message {
+    fn header(&self) -> MessageHeader {
+        self.header
+    }
+
+    fn static_account_keys(&self) -> &[Pubkey] {
+        &self.account_keys
+    }
+
+    fn instructions(&self) -> &[CompiledInstruction] {
+        &self.instructions
+    }
+
+    fn address_table_lookups(&self) -> &[MessageAddressTableLookup] {
+        &self.address_table_lookups
+    }
+}
+
+/// `LoadableMessage` for the candidate versions. The v1 and v2 headers convert
+/// into the `MessageHeader` they carry.
+macro_rules! impl_loadable_message {
+    ($($version:ident),*) => {$(
+        impl LoadableMessage for $version::Message {
+            fn header(&self) -> MessageHeader {
+                self.header.into()
+            }
+
+            fn static_account_keys(&self) -> &[Pubkey] {
+                &self.account_keys
+            }
+
+            fn instructions(&self) -> &[CompiledInstruction] {
+                &self.instructions
+            }
+
+            fn address_table_lookups(&self) -> &[MessageAddressTableLookup] {
+                &self.address_table_lookups
+            }
+        }
+    )*};
+}
+
+impl_loadable_message!(v1, v2, v3, v4, v5);
+#[cfg(feature = "v3b")]
+impl_loadable_message!(v3b);
+
+/// Source of address lookup table contents.
+pub trait AddressLookupTableProvider {
+    /// The addresses stored in the table at `table_key`, or `None` if there is
+    /// no such table.
+    fn lookup_table(&self, table_key: &Pubkey) -> Option<&[Pubkey]>;
+}
+
+/// Tables held in memory, keyed by table address.
+impl AddressLookupTableProvider for HashMap<Pubkey, Vec<Pubkey>> {
+    fn lookup_table(&self, table_key: &Pubkey) -> Option<&[Pubkey]> {
+        self.get(table_key).map(Vec::as_slice)
+    }
+}
+
+/// Resolve `lookups` against the tables in `provider`. Writable addresses
+/// from every lookup come first, then readonly ones, in lookup order.
+pub fn load_addresses<P: AddressLookupTableProvider + ?Sized>(
+    provider: &P,
+    lookups: &[MessageAddressTableLookup],
+) -> Result<LoadedAddresses, AddressLoaderError> {
+    lookups
+        .iter()
+        .map(|lookup| {
+            let table = provider
+                .lookup_table(&lookup.account_key)
+                .ok_or(AddressLoaderError::LookupTableAccountNotFound)?;
+            let resolve = |indexes: &[u8]| {
+                indexes
+                    .iter()
+                    .map(|&index| table.get(usize::from(index)).copied())
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or(AddressLoaderError::InvalidLookupIndex)
+            };
+            Ok(LoadedAddresses {
+                writable: resolve(&lookup.writable_indexes)?,
+                readonly: resolve(&lookup.readonly_indexes)?,
+            })
+        })
+        .collect()
+}
+
+/// The default number of accounts a transaction may lock, as in the runtime.
+pub const MAX_TX_ACCOUNT_LOCKS: usize = 128;
+
+/// The accounts a transaction locks, split by lock kind.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TransactionAccountLocks<'a> {
+    pub readonly: Vec<&'a Pubkey>,
+    pub writable: Vec<&'a Pubkey>,
+}
+
+/// A candidate message and the addresses loaded by its lookup tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedMessage<'a, M: LoadableMessage> {
+    pub message: Cow<'a, M>,
+    pub loaded_addresses: Cow<'a, LoadedAddresses>,
+    /// Whether each key in [`Self::account_keys`] is write locked, after
+    /// demotion.
+    pub is_writable_account_cache: Vec<bool>,
+}
+
+impl<'a, M: LoadableMessage> LoadedMessage<'a, M> {
+    pub fn new(
+        message: M,
+        loaded_addresses: LoadedAddresses,
+        reserved_account_keys: &HashSet<Pubkey>,
+    ) -> Self {
+        let mut loaded_message = Self {
+            message: Cow::Owned(message),
+            loaded_addresses: Cow::Owned(loaded_addresses),
+            is_writable_account_cache: vec![],
+        };
+        loaded_message.set_is_writable_account_cache(reserved_account_keys);
+        loaded_message
+    }
+
+    pub fn new_borrowed(
+        message: &'a M,
+        loaded_addresses: &'a LoadedAddresses,
+        reserved_account_keys: &HashSet<Pubkey>,
+    ) -> Self {
+        let mut loaded_message = Self {
+            message: Cow::Borrowed(message),
+            loaded_addresses: Cow::Borrowed(loaded_addresses),
+            is_writable_account_cache: vec![],
+        };
+        loaded_message.set_is_writable_account_cache(reserved_account_keys);
+        loaded_message
+    }
+
+    /// Resolve the message's lookups against `provider` and load it.
+    pub fn try_load<P: AddressLookupTableProvider + ?Sized>(
+        message: M,
+        provider: &P,
+        reserved_account_keys: &HashSet<Pubkey>,
+    ) -> Result<Self, AddressLoaderError> {
+        let loaded_addresses = load_addresses(provider, message.address_table_lookups())?;
+        Ok(Self::new(message, loaded_addresses, reserved_account_keys))
+    }
+
+    fn account_roles(&self) -> AccountRoles<'_> {
+        AccountRoles {
+            header: self.message.header(),
+            account_keys: self.message.static_account_keys(),
+            instructions: self.message.instructions(),
+            address_table_lookups: self.message.address_table_lookups(),
+        }
+    }
+
+    fn set_is_writable_account_cache(&mut self, reserved_account_keys: &HashSet<Pubkey>) {
+        let roles = self.account_roles();
+        let account_keys = self.account_keys();
+        let is_upgradeable_loader_present = self.is_upgradeable_loader_present();
+        self.is_writable_account_cache = account_keys
+            .iter()
+            .enumerate()
+            .map(|(index, key)| {
+                roles.is_writable(
+                    index,
+                    Some(key),
+                    Some(reserved_account_keys),
+                    is_upgradeable_loader_present,
+                )
+            })
+            .collect();
+    }
+
+    /// Static keys followed by the writable, then readonly, loaded addresses.
+    pub fn account_keys(&self) -> AccountKeys<'_> {
+        AccountKeys::new(
+            self.message.static_account_keys(),
+            Some(&self.loaded_addresses),
+        )
+    }
+
+    pub fn static_account_keys(&self) -> &[Pubkey] {
+        self.message.static_account_keys()
+    }
+
+    pub fn has_duplicates(&self) -> bool {
+        let mut unique = HashSet::new();
+        self.account_keys().iter().any(|key| !unique.insert(key))
+    }
+
+    pub fn is_writable(&self, key_index: usize) -> bool {
+        self.is_writable_account_cache
+            .get(key_index)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    pub fn is_signer(&self, key_index: usize) -> bool {
+        self.account_roles().is_signer(key_index)
+    }
+
+    /// Whether the account at `key_index` loses its write lock for being
+    /// invoked as a program.
+    pub fn demote_program_id(&self, key_index: usize) -> bool {
+        self.is_key_called_as_program(key_index) && !self.is_upgradeable_loader_present()
+    }
+
+    pub fn is_key_called_as_program(&self, key_index: usize) -> bool {
+        self.account_roles().is_key_called_as_program(key_index)
+    }
+
+    pub fn is_upgradeable_loader_present(&self) -> bool {
+        self.account_keys()
+            .iter()
+            .any(|key| *key == bpf_loader_upgradeable::id())
+    }
+
+    /// The static and loaded accounts this message locks. Fails if there are
+    /// more than `tx_account_lock_limit` keys or any key appears twice.
+    pub fn get_account_locks(
+        &self,
+        tx_account_lock_limit: usize,
+    ) -> Result<TransactionAccountLocks<'_>, TransactionError> {
+        // the cheap length check goes first, as in the runtime
+        let account_keys = self.account_keys();
+        if account_keys.len() > tx_account_lock_limit {
+            return Err(TransactionError::TooManyAccountLocks);
+        }
+        if self.has_duplicates() {
+            return Err(TransactionError::AccountLoadedTwice);
+        }
+
+        let mut locks = TransactionAccountLocks::default();
+        for (index, key) in account_keys.iter().enumerate() {
+            if self.is_writable(index) {
+                locks.writable.push(key);
+            } else {
+                locks.readonly.push(key);
+            }
+        }
+        Ok(locks)
+    }
+}
+
+#[test]
+fn test_loaded_message() {
+    use solana_hash::Hash;
+
+    let table_key = Pubkey::new_unique();
+    let table: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let tables = HashMap::from([(table_key, table.clone())]);
+    let reserved = table[3];
+
+    let payer = Pubkey::new_unique();
+    let program = Pubkey::new_unique();
+    let message = v0::Message {
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 1,
+        },
+        account_keys: vec![payer, program],
+        recent_blockhash: Hash::new_unique(),
+        instructions: vec![CompiledInstruction::new_from_raw_parts(
+            1,
+            vec![],
+            vec![0, 2, 3, 4],
+        )],
+        address_table_lookups: vec![MessageAddressTableLookup {
+            account_key: table_key,
+            writable_indexes: vec![2, 3],
+            readonly_indexes: vec![0],
+        }],
+    };
+    let message = v3::Message::from_v0(&message);
+    let reserved_account_keys = HashSet::from([reserved]);
+
+    let loaded = LoadedMessage::try_load(message.clone(), &tables, &reserved_account_keys).unwrap();
+    assert_eq!(
+        loaded.account_keys().iter().copied().collect::<Vec<_>>(),
+        vec![payer, program, table[2], table[3], table[0]]
+    );
+    assert_eq!(loaded.static_account_keys(), &[payer, program]);
+    assert!(!loaded.has_duplicates());
+    assert!(loaded.is_signer(0));
+    assert!(!loaded.is_signer(1));
+    assert!(loaded.is_key_called_as_program(1));
+    // payer, readonly program, writable, reserved, readonly loaded, past the end
+    let writable: Vec<bool> = (0..6).map(|index| loaded.is_writable(index)).collect();
+    assert_eq!(writable, vec![true, false, true, false, false, false]);
+
+    // the upgradeable loader can come from a table and still lift demotion
+    let mut with_loader = message.clone();
+    with_loader.header.num_readonly_unsigned_accounts = 0;
+    let tables = HashMap::from([(
+        table_key,
+        vec![bpf_loader_upgradeable::id(), table[1], table[2], table[3]],
+    )]);
+    let loaded = LoadedMessage::try_load(with_loader, &tables, &HashSet::new()).unwrap();
+    assert!(loaded.is_upgradeable_loader_present());
+    assert!(!loaded.demote_program_id(1));
+    assert!(loaded.is_writable(1));
+
+    let locks = loaded.get_account_locks(MAX_TX_ACCOUNT_LOCKS).unwrap();
+    assert_eq!(locks.writable, vec![&payer, &program, &table[2], &table[3]]);
+    assert_eq!(locks.readonly, vec![&bpf_loader_upgradeable::id()]);
+    assert_eq!(
+        loaded.get_account_locks(4),
+        Err(TransactionError::TooManyAccountLocks)
+    );
+
+    // a table can't hand back a key the message already has
+    let tables = HashMap::from([(table_key, vec![table[0], table[1], payer, table[3]])]);
+    let loaded = LoadedMessage::try_load(message.clone(), &tables, &HashSet::new()).unwrap();
+    assert!(loaded.has_duplicates());
+    assert_eq!(
+        loaded.get_account_locks(MAX_TX_ACCOUNT_LOCKS),
+        Err(TransactionError::AccountLoadedTwice)
+    );
+    // breaking both rules reports the lock limit
+    assert_eq!(
+        loaded.get_account_locks(4),
+        Err(TransactionError::TooManyAccountLocks)
+    );
+
+    // missing tables and out of range indexes fail to load
+    assert_eq!(
+        LoadedMessage::try_load(message.clone(), &HashMap::new(), &reserved_account_keys),
+        Err(AddressLoaderError::LookupTableAccountNotFound)
+    );
+    let short_tables = HashMap::from([(table_key, table[..3].to_vec())]);
+    assert_eq!(
+        LoadedMessage::try_load(message, &short_tables, &reserved_account_keys),
+        Err(AddressLoaderError::InvalidLookupIndex)
+    );
+}
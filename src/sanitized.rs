@@ -0,0 +1,280 @@
+//! Sanitized, address-loaded messages with their derived data computed once.
+//!
+//! A [`SanitizedMessage`] can only be built by sanitizing a message, checking
+//! its compute budget against its fee cap and resolving its lookup tables, so
+//! holders can rely on all three having succeeded. Legacy and v0 messages get
+//! the same wrapper, and consumers that only need the cached data can be
+//! generic over [`SanitizedMessageInfo`].
+
+use std::collections::HashSet;
+
+use solana_hash::Hash;
+use solana_message::{
+    AccountKeys, MESSAGE_VERSION_PREFIX, compiled_instruction::CompiledInstruction, legacy, v0,
+};
+use solana_pubkey::Pubkey;
+use solana_sanitize::{Sanitize, SanitizeError};
+use solana_transaction_error::TransactionError;
+
+#[cfg(feature = "v3b")]
+use crate::v3b;
+use crate::{
+    compute_budget::ComputeBudgetRequest,
+    loaded::{AddressLookupTableProvider, LoadedMessage},
+    svm::SvmMessage,
+    v1, v2, v3, v4, v5,
+    versioned::VersionedMessage,
+};
+
+/// A message version that can be sanitized into a [`SanitizedMessage`].
+pub trait SanitizableMessage: SvmMessage + 'static {
+    fn sanitize(&self) -> Result<(), SanitizeError>;
+    /// Blake3 hash of the serialized message, see [`VersionedMessage::hash`].
+    fn hash(&self) -> Hash;
+}
+
+impl SanitizableMessage for legacy::Message {
+    fn sanitize(&self) -> Result<(), SanitizeError> {
+        Sanitize::sanitize(self)
+    }
+
+    fn hash(&self) -> Hash {
+        VersionedMessage::hash_raw_message(&bincode::serialize(self).unwrap())
+    }
+}
+
+impl SanitizableMessage for v0::Message {
+    fn sanitize(&self) -> Result<(), SanitizeError> {
+        v0::Message::sanitize(self)
+    }
+
+    fn hash(&self) -> Hash {
+        VersionedMessage::hash_raw_message(
+            &bincode::serialize(&(MESSAGE_VERSION_PREFIX, self)).unwrap(),
+        )
+    }
+}
+
+macro_rules! impl_sanitizable_message {
+    ($($version:ident),*) => {$(
+        impl SanitizableMessage for $version::Message {
+            fn sanitize(&self) -> Result<(), SanitizeError> {
+                $version::Message::sanitize(self)
+            }
+
+            fn hash(&self) -> Hash {
+                $version::Message::hash(self)
+            }
+        }
+    )*};
+}
+
+impl_sanitizable_message!(v1, v2, v3, v4, v5);
+#[cfg(feature = "v3b")]
+impl_sanitizable_message!(v3b);
+
+/// Read access to the data cached by a sanitized message, whatever its
+/// version.
+pub trait SanitizedMessageInfo {
+    fn fee_payer(&self) -> &Pubkey;
+    fn message_hash(&self) -> &Hash;
+    fn compute_budget_request(&self) -> &ComputeBudgetRequest;
+    fn recent_blockhash(&self) -> &Hash;
+    /// Static keys followed by the loaded addresses.
+    fn account_keys(&self) -> AccountKeys<'_>;
+    fn instructions(&self) -> &[CompiledInstruction];
+    fn num_required_signatures(&self) -> usize;
+    fn is_signer(&self, key_index: usize) -> bool;
+    /// Whether the account at `key_index` is write locked, after demotion.
+    fn is_writable(&self, key_index: usize) -> bool;
+}
+
+/// A message that sanitized, has a valid compute budget and whose lookup
+/// tables resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SanitizedMessage<M: SanitizableMessage> {
+    loaded: LoadedMessage<'static, M>,
+    compute_budget_request: ComputeBudgetRequest,
+    message_hash: Hash,
+}
+
+pub type SanitizedLegacyMessage = SanitizedMessage<legacy::Message>;
+pub type SanitizedV0Message = SanitizedMessage<v0::Message>;
+pub type SanitizedV1Message = SanitizedMessage<v1::Message>;
+pub type SanitizedV2Message = SanitizedMessage<v2::Message>;
+pub type SanitizedV3Message = SanitizedMessage<v3::Message>;
+pub type SanitizedV4Message = SanitizedMessage<v4::Message>;
+pub type SanitizedV5Message = SanitizedMessage<v5::Message>;
+#[cfg(feature = "v3b")]
+pub type SanitizedV3bMessage = SanitizedMessage<v3b::Message>;
+
+impl<M: SanitizableMessage> SanitizedMessage<M> {
+    pub fn try_new<P: AddressLookupTableProvider + ?Sized>(
+        message: M,
+        provider: &P,
+        reserved_account_keys: &HashSet<Pubkey>,
+    ) -> Result<Self, TransactionError> {
+        message
+            .sanitize()
+            .map_err(|_| TransactionError::SanitizeFailure)?;
+        let compute_budget_request = message.compute_budget_request()?;
+        compute_budget_request
+            .check_max_fee()
+            .map_err(|_| TransactionError::SanitizeFailure)?;
+        let message_hash = message.hash();
+        let loaded = LoadedMessage::try_load(message, provider, reserved_account_keys)?;
+        Ok(Self {
+            loaded,
+            compute_budget_request,
+            message_hash,
+        })
+    }
+
+    pub fn message(&self) -> &M {
+        &self.loaded.message
+    }
+
+    pub fn loaded_message(&self) -> &LoadedMessage<'static, M> {
+        &self.loaded
+    }
+}
+
+impl<M: SanitizableMessage> SanitizedMessageInfo for SanitizedMessage<M> {
+    fn fee_payer(&self) -> &Pubkey {
+        // sanitized messages have at least one writable signer
+        &self.loaded.static_account_keys()[0]
+    }
+
+    fn message_hash(&self) -> &Hash {
+        &self.message_hash
+    }
+
+    fn compute_budget_request(&self) -> &ComputeBudgetRequest {
+        &self.compute_budget_request
+    }
+
+    fn recent_blockhash(&self) -> &Hash {
+        self.loaded.message.recent_blockhash()
+    }
+
+    fn account_keys(&self) -> AccountKeys<'_> {
+        self.loaded.account_keys()
+    }
+
+    fn instructions(&self) -> &[CompiledInstruction] {
+        self.loaded.message.instructions()
+    }
+
+    fn num_required_signatures(&self) -> usize {
+        usize::from(self.loaded.message.header().num_required_signatures)
+    }
+
+    fn is_signer(&self, key_index: usize) -> bool {
+        self.loaded.is_signer(key_index)
+    }
+
+    fn is_writable(&self, key_index: usize) -> bool {
+        self.loaded.is_writable(key_index)
+    }
+}
+
+#[test]
+fn test_sanitized_message() {
+    use std::collections::HashMap;
+
+    use solana_compute_budget_interface::ComputeBudgetInstruction;
+    use solana_message::MessageHeader;
+
+    let payer = Pubkey::new_unique();
+    let program = Pubkey::new_unique();
+    let budget_instruction = |instruction: solana_instruction::Instruction| {
+        CompiledInstruction::new_from_raw_parts(2, instruction.data, vec![])
+    };
+    let message = v0::Message {
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 2,
+        },
+        account_keys: vec![payer, program, solana_compute_budget_interface::ID],
+        recent_blockhash: Hash::new_unique(),
+        instructions: vec![
+            budget_instruction(ComputeBudgetInstruction::set_compute_unit_limit(1_000)),
+            budget_instruction(
+                ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(2_000),
+            ),
+            CompiledInstruction::new_from_raw_parts(1, vec![], vec![0]),
+        ],
+        address_table_lookups: vec![],
+    };
+    let tables = HashMap::new();
+    let reserved_account_keys = HashSet::new();
+
+    let v0 = SanitizedV0Message::try_new(message.clone(), &tables, &reserved_account_keys).unwrap();
+    let v1 = SanitizedV1Message::try_new(
+        v1::Message::from_v0(&message),
+        &tables,
+        &reserved_account_keys,
+    )
+    .unwrap();
+    let v3 = SanitizedV3Message::try_new(
+        v3::Message::from_v0(&message),
+        &tables,
+        &reserved_account_keys,
+    )
+    .unwrap();
+
+    // every version caches the same data for the same message
+    let infos: [&dyn SanitizedMessageInfo; 3] = [&v0, &v1, &v3];
+    for info in infos {
+        assert_eq!(*info.fee_payer(), payer);
+        assert_eq!(info.recent_blockhash(), &message.recent_blockhash);
+        assert_eq!(
+            *info.compute_budget_request(),
+            ComputeBudgetRequest {
+                compute_unit_limit: Some(1_000),
+                loaded_accounts_data_limit: Some(2_000),
+                ..ComputeBudgetRequest::default()
+            }
+        );
+        assert_eq!(info.num_required_signatures(), 1);
+        assert!(info.is_signer(0) && info.is_writable(0));
+        assert!(!info.is_signer(1) && !info.is_writable(1));
+    }
+    assert_eq!(
+        *v0.message_hash(),
+        VersionedMessage::V0(message.clone()).hash()
+    );
+    assert_eq!(*v1.message_hash(), v1.message().hash());
+    assert_eq!(*v3.message_hash(), v3.message().hash());
+    // v3 folds both budget instructions, v1 keeps the one it can't express
+    assert_eq!(v0.instructions().len(), 3);
+    assert_eq!(v1.instructions().len(), 2);
+    assert_eq!(v3.instructions().len(), 1);
+    assert_eq!(v3.account_keys().len(), 2);
+
+    // construction fails on unsanitary messages, bad budgets and lookups
+    let mut unsanitary = v3.message().clone();
+    unsanitary.header.num_required_signatures = 0;
+    assert_eq!(
+        SanitizedV3Message::try_new(unsanitary, &tables, &reserved_account_keys),
+        Err(TransactionError::SanitizeFailure)
+    );
+    let mut duplicate_budget = message.clone();
+    duplicate_budget.instructions[1] =
+        budget_instruction(ComputeBudgetInstruction::set_compute_unit_limit(1));
+    assert_eq!(
+        SanitizedV0Message::try_new(duplicate_budget, &tables, &reserved_account_keys),
+        Err(TransactionError::DuplicateInstruction(1))
+    );
+    let mut missing_table = v3.message().clone();
+    missing_table.address_table_lookups = vec![v0::MessageAddressTableLookup {
+        account_key: Pubkey::new_unique(),
+        writable_indexes: vec![0],
+        readonly_indexes: vec![],
+    }];
+    assert_eq!(
+        SanitizedV3Message::try_new(missing_table, &tables, &reserved_account_keys),
+        Err(TransactionError::AddressLookupTableNotFound)
+    );
+}
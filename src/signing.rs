@@ -0,0 +1,269 @@
+//! Signing and verifying transactions with ed25519 keys.
+//!
+//! Every signature covers [`VersionedMessage::serialize_for_signing`], the
+//! message bytes including the version prefix, so a signature for one version
+//! can never be replayed as another.
+
+use solana_short_vec::ShortU16;
+use solana_signature::Signature;
+use solana_signer::{SignerError, signers::Signers};
+
+use crate::{
+    transaction::VersionedTransaction,
+    versioned::{ParseError, VersionedMessage},
+};
+
+/// A transaction that can be signed by, and checked against, the required
+/// signers of its message.
+pub trait SignableTransaction {
+    /// Sign with each of `signers`, storing every signature at the position of
+    /// the signer's key among the message's required signers. Signers may sign
+    /// in any order and over several calls; unsigned positions hold
+    /// [`Signature::default`].
+    fn sign_message<T: Signers + ?Sized>(&mut self, signers: &T) -> Result<(), SignerError>;
+
+    /// Whether there is exactly one signature per required signer and every
+    /// signature is valid for its account key.
+    fn verify(&self) -> bool;
+
+    /// Whether each of the first `num_required_signatures` account keys signed
+    /// the message, in account key order. A missing signature is `false`.
+    fn verify_with_results(&self) -> Vec<bool>;
+}
+
+impl VersionedTransaction {
+    /// Sign `message` with `signers`, which must cover every required signer.
+    pub fn try_new<T: Signers + ?Sized>(
+        message: VersionedMessage,
+        signers: &T,
+    ) -> Result<Self, SignerError> {
+        let mut transaction = VersionedTransaction {
+            signatures: vec![],
+            message,
+        };
+        transaction.sign_message(signers)?;
+        if transaction
+            .signatures
+            .iter()
+            .any(|signature| *signature == Signature::default())
+        {
+            return Err(SignerError::NotEnoughSigners);
+        }
+        Ok(transaction)
+    }
+}
+
+impl SignableTransaction for VersionedTransaction {
+    fn sign_message<T: Signers + ?Sized>(&mut self, signers: &T) -> Result<(), SignerError> {
+        let num_required_signatures = usize::from(self.message.header().num_required_signatures);
+        let signer_keys = self
+            .message
+            .static_account_keys()
+            .get(..num_required_signatures)
+            .ok_or(SignerError::InvalidInput(
+                "message has fewer account keys than required signatures".to_string(),
+            ))?;
+
+        let positions = signers
+            .try_pubkeys()?
+            .iter()
+            .map(|pubkey| {
+                signer_keys
+                    .iter()
+                    .position(|key| key == pubkey)
+                    .ok_or(SignerError::KeypairPubkeyMismatch)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let signatures = signers.try_sign_message(&self.message.serialize_for_signing())?;
+        self.signatures
+            .resize(num_required_signatures, Signature::default());
+        for (position, signature) in positions.into_iter().zip(signatures) {
+            self.signatures[position] = signature;
+        }
+        Ok(())
+    }
+
+    fn verify(&self) -> bool {
+        let num_required_signatures = usize::from(self.message.header().num_required_signatures);
+        self.signatures.len() == num_required_signatures
+            && self.verify_with_results().into_iter().all(|valid| valid)
+    }
+
+    fn verify_with_results(&self) -> Vec<bool> {
+        verify_signatures(
+            &self.signatures,
+            &self.message,
+            &self.message.serialize_for_signing(),
+        )
+    }
+}
+
+impl VersionedTransaction {
+    /// Decode a transaction from its wire bytes and check each required
+    /// signature against the message bytes exactly as they were received.
+    ///
+    /// Decoding is strict, so the only encoding of a message that decodes is
+    /// its canonical one and a re-encoded message can't reuse signatures. The
+    /// received bytes are still what the signatures are checked against, never
+    /// a re-encoding of the decoded message.
+    pub fn verify_packet(bytes: &[u8]) -> Result<(Self, Vec<bool>), ParseError> {
+        let transaction = Self::deserialize(bytes)?;
+        let message_bytes = &bytes[transaction.message_offset()..];
+        let results =
+            verify_signatures(&transaction.signatures, &transaction.message, message_bytes);
+        Ok((transaction, results))
+    }
+
+    /// Offset of the message in the serialized transaction: the short vec
+    /// signature count followed by the signatures.
+    fn message_offset(&self) -> usize {
+        let len = bincode::serialized_size(&ShortU16(self.signatures.len() as u16)).unwrap();
+        len as usize + self.signatures.len() * size_of::<Signature>()
+    }
+}
+
+fn verify_signatures(
+    signatures: &[Signature],
+    message: &VersionedMessage,
+    message_bytes: &[u8],
+) -> Vec<bool> {
+    let num_required_signatures = usize::from(message.header().num_required_signatures);
+    message
+        .static_account_keys()
+        .iter()
+        .take(num_required_signatures)
+        .enumerate()
+        .map(|(index, pubkey)| {
+            signatures
+                .get(index)
+                .is_some_and(|signature| signature.verify(pubkey.as_ref(), message_bytes))
+        })
+        .collect()
+}
+
+#[test]
+fn test_sign_and_verify() {
+    use solana_hash::Hash;
+    use solana_keypair::Keypair;
+    use solana_message::MessageHeader;
+    use solana_signer::Signer;
+
+    use crate::v3;
+
+    let payer = Keypair::new();
+    let cosigner = Keypair::new();
+    let message = VersionedMessage::V3(v3::Message {
+        compute_budget_header: v3::ComputeBudgetHeader::new(Some(1), Some(2), None, None),
+        header: MessageHeader {
+            num_required_signatures: 2,
+            num_readonly_signed_accounts: 1,
+            num_readonly_unsigned_accounts: 0,
+        },
+        account_keys: vec![payer.pubkey(), cosigner.pubkey()],
+        recent_blockhash: Hash::new_unique(),
+        instructions: vec![],
+        address_table_lookups: vec![],
+    });
+
+    // signers are placed by key, not by the order they are passed in
+    let transaction = VersionedTransaction::try_new(message.clone(), &[&cosigner, &payer]).unwrap();
+    assert!(transaction.verify());
+    assert_eq!(
+        transaction.signatures[0],
+        payer.sign_message(&message.serialize_for_signing())
+    );
+    assert_eq!(
+        VersionedTransaction::try_new(message.clone(), &[&payer]),
+        Err(SignerError::NotEnoughSigners)
+    );
+    assert_eq!(
+        VersionedTransaction::try_new(message.clone(), &[&payer, &Keypair::new()]),
+        Err(SignerError::KeypairPubkeyMismatch)
+    );
+
+    // partial signing leaves a placeholder that fails verification
+    let mut partial = VersionedTransaction {
+        signatures: vec![],
+        message,
+    };
+    partial.sign_message(&[&payer]).unwrap();
+    assert!(!partial.verify());
+    assert_eq!(partial.verify_with_results(), vec![true, false]);
+    partial.sign_message(&[&cosigner]).unwrap();
+    assert_eq!(partial, transaction);
+
+    // the signature covers the whole message, including the budget header
+    let mut tampered = transaction;
+    let VersionedMessage::V3(message) = &mut tampered.message else {
+        unreachable!()
+    };
+    message.compute_budget_header = v3::ComputeBudgetHeader::new(Some(1), Some(3), None, None);
+    assert!(!tampered.verify());
+}
+
+#[test]
+fn test_verify_packet() {
+    use solana_hash::Hash;
+    use solana_keypair::Keypair;
+    use solana_signer::Signer;
+
+    use crate::{v2, versioned::MessageVersion};
+
+    let payer = Keypair::new();
+    let message = VersionedMessage::V2(v2::Message {
+        header: v2::MessageHeader {
+            compute_unit_price: 5,
+            compute_unit_limit: 6,
+            loaded_accounts_data_limit: 0,
+            requested_heap_bytes: 0,
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 0,
+        },
+        account_keys: vec![payer.pubkey()],
+        recent_blockhash: Hash::new_unique(),
+        instructions: vec![],
+        address_table_lookups: vec![],
+    });
+    let transaction = VersionedTransaction::try_new(message, &[&payer]).unwrap();
+    let bytes = transaction.serialize();
+    assert_eq!(
+        VersionedTransaction::verify_packet(&bytes),
+        Ok((transaction.clone(), vec![true]))
+    );
+
+    // a flipped bit in the message invalidates the signature
+    let mut corrupted = bytes.clone();
+    let blockhash_byte = bytes.len() - 3;
+    corrupted[blockhash_byte] ^= 1;
+    assert_eq!(
+        VersionedTransaction::verify_packet(&corrupted).unwrap().1,
+        vec![false]
+    );
+
+    // the same message re-encoded as another version doesn't verify
+    let mut reencoded = transaction.clone();
+    reencoded.message = transaction.message.convert(MessageVersion::V3).unwrap();
+    assert_eq!(reencoded.verify_with_results(), vec![false]);
+    assert_eq!(
+        VersionedTransaction::verify_packet(&reencoded.serialize())
+            .unwrap()
+            .1,
+        vec![false]
+    );
+
+    // non-canonical encodings of the same message don't decode at all
+    let mut padded = bytes.clone();
+    padded.push(0);
+    assert_eq!(
+        VersionedTransaction::verify_packet(&padded),
+        Err(ParseError::InvalidEncoding)
+    );
+    let mut long_length = vec![0x81, 0x00];
+    long_length.extend_from_slice(&bytes[1..]);
+    assert_eq!(
+        VersionedTransaction::verify_packet(&long_length),
+        Err(ParseError::InvalidEncoding)
+    );
+}
@@ -0,0 +1,169 @@
+//! One view of a message for transaction processing, whatever its version.
+//!
+//! [`SvmMessage`] is implemented for the candidate versions and for the SDK's
+//! legacy and v0 messages, so processing code can be written once against it.
+//! Like the runtime's own message views it assumes the message has been
+//! sanitized: the fee payer and instruction program ids are indexed directly.
+
+use solana_hash::Hash;
+use solana_message::{compiled_instruction::CompiledInstruction, legacy, v0};
+use solana_pubkey::Pubkey;
+use solana_transaction_error::TransactionError;
+
+#[cfg(feature = "v3b")]
+use crate::v3b;
+use crate::{compute_budget::ComputeBudgetRequest, loaded::LoadableMessage, v1, v2, v3, v4, v5};
+
+pub trait SvmMessage: LoadableMessage {
+    fn recent_blockhash(&self) -> &Hash;
+
+    /// The compute budget requested by the header, if the version has one,
+    /// and any compute budget program instructions.
+    fn compute_budget_request(&self) -> Result<ComputeBudgetRequest, TransactionError>;
+
+    /// The account paying the transaction fee, always the first static key.
+    fn fee_payer(&self) -> &Pubkey {
+        &self.static_account_keys()[0]
+    }
+
+    fn num_required_signatures(&self) -> usize {
+        usize::from(self.header().num_required_signatures)
+    }
+
+    /// Static account keys, in index order. Addresses from lookup tables
+    /// follow them once loaded, see [`crate::loaded::LoadedMessage`].
+    fn account_keys(&self) -> impl Iterator<Item = &Pubkey> {
+        self.static_account_keys().iter()
+    }
+
+    /// Each instruction with the program it invokes.
+    fn program_instructions_iter(&self) -> impl Iterator<Item = (&Pubkey, &CompiledInstruction)> {
+        let account_keys = self.static_account_keys();
+        self.instructions().iter().map(|instruction| {
+            (
+                &account_keys[usize::from(instruction.program_id_index)],
+                instruction,
+            )
+        })
+    }
+}
+
+impl SvmMessage for legacy::Message {
+    fn recent_blockhash(&self) -> &Hash {
+        &self.recent_blockhash
+    }
+
+    fn compute_budget_request(&self) -> Result<ComputeBudgetRequest, TransactionError> {
+        ComputeBudgetRequest::default().process_instructions(&self.account_keys, &self.instructions)
+    }
+}
+
+impl SvmMessage for v0::Message {
+    fn recent_blockhash(&self) -> &Hash {
+        &self.recent_blockhash
+    }
+
+    fn compute_budget_request(&self) -> Result<ComputeBudgetRequest, TransactionError> {
+        ComputeBudgetRequest::default().process_instructions(&self.account_keys, &self.instructions)
+    }
+}
+
+macro_rules! impl_svm_message {
+    ($($version:ident),*) => {$(
+        impl SvmMessage for $version::Message {
+            fn recent_blockhash(&self) -> &Hash {
+                &self.recent_blockhash
+            }
+
+            fn compute_budget_request(&self) -> Result<ComputeBudgetRequest, TransactionError> {
+                $version::Message::compute_budget_request(self)
+            }
+        }
+    )*};
+}
+
+impl_svm_message!(v1, v2, v3, v4, v5);
+#[cfg(feature = "v3b")]
+impl_svm_message!(v3b);
+
+#[test]
+fn test_svm_message() {
+    use solana_compute_budget_interface::ComputeBudgetInstruction;
+    use solana_message::MessageHeader;
+
+    let payer = Pubkey::new_unique();
+    let program = Pubkey::new_unique();
+    let limit = ComputeBudgetInstruction::set_compute_unit_limit(300);
+    let heap = ComputeBudgetInstruction::request_heap_frame(64 * 1024);
+    let legacy = legacy::Message {
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 2,
+        },
+        account_keys: vec![payer, program, solana_compute_budget_interface::ID],
+        recent_blockhash: Hash::new_unique(),
+        instructions: vec![
+            CompiledInstruction::new_from_raw_parts(2, limit.data, vec![]),
+            CompiledInstruction::new_from_raw_parts(2, heap.data, vec![]),
+            CompiledInstruction::new_from_raw_parts(1, vec![9], vec![0]),
+        ],
+    };
+    let v0 = v0::Message {
+        header: legacy.header,
+        account_keys: legacy.account_keys.clone(),
+        recent_blockhash: legacy.recent_blockhash,
+        instructions: legacy.instructions.clone(),
+        address_table_lookups: vec![],
+    };
+
+    // everything a processor would read, with no per-version branches
+    fn summarize<M: SvmMessage>(
+        message: &M,
+    ) -> (Pubkey, usize, Hash, ComputeBudgetRequest, Vec<Pubkey>) {
+        let user_programs = message
+            .program_instructions_iter()
+            .map(|(program_id, _)| *program_id)
+            .filter(|program_id| !solana_compute_budget_interface::check_id(program_id))
+            .collect();
+        (
+            *message.fee_payer(),
+            message.num_required_signatures(),
+            *message.recent_blockhash(),
+            message.compute_budget_request().unwrap(),
+            user_programs,
+        )
+    }
+
+    let expected = summarize(&legacy);
+    assert_eq!(
+        expected,
+        (
+            payer,
+            1,
+            legacy.recent_blockhash,
+            ComputeBudgetRequest {
+                compute_unit_limit: Some(300),
+                requested_heap_bytes: Some(64 * 1024),
+                ..ComputeBudgetRequest::default()
+            },
+            vec![program],
+        )
+    );
+    assert_eq!(summarize(&v0), expected);
+    assert_eq!(summarize(&v1::Message::from_v0(&v0)), expected);
+    assert_eq!(summarize(&v2::Message::from_v0(&v0)), expected);
+    assert_eq!(summarize(&v3::Message::from_v0(&v0)), expected);
+    assert_eq!(summarize(&v4::Message::from_v0(&v0)), expected);
+    assert_eq!(summarize(&v5::Message::from_v0(&v0)), expected);
+    #[cfg(feature = "v3b")]
+    assert_eq!(summarize(&v3b::Message::from_v0(&v0)), expected);
+
+    // v3 drops the folded compute budget program from its keys
+    let v3 = v3::Message::from_v0(&v0);
+    assert_eq!(
+        SvmMessage::account_keys(&v3).collect::<Vec<_>>(),
+        vec![&payer, &program]
+    );
+    assert!(v3.address_table_lookups().is_empty());
+}
@@ -0,0 +1,90 @@
+//! A signed transaction carrying any [`VersionedMessage`].
+
+use bincode::Options;
+use serde_derive::{Deserialize, Serialize};
+use solana_sanitize::SanitizeError;
+use solana_signature::Signature;
+
+use crate::versioned::{ParseError, VersionedMessage, wire_options};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionedTransaction {
+    /// One signature per required signer, in account key order.
+    #[serde(with = "solana_short_vec")]
+    pub signatures: Vec<Signature>,
+
+    pub message: VersionedMessage,
+}
+
+impl VersionedTransaction {
+    /// Decode a transaction from its wire bytes without sanitizing it.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, ParseError> {
+        wire_options()
+            .deserialize(bytes)
+            .map_err(|_| ParseError::InvalidEncoding)
+    }
+
+    /// Decode and sanitize a transaction from its wire bytes.
+    pub fn parse(bytes: &[u8]) -> Result<Self, ParseError> {
+        let transaction = Self::deserialize(bytes)?;
+        transaction.sanitize()?;
+        Ok(transaction)
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
+    }
+
+    pub fn sanitize(&self) -> Result<(), SanitizeError> {
+        self.message.sanitize()?;
+
+        // once the message is sanitized it has at least one required signer
+        if usize::from(self.message.header().num_required_signatures) > self.signatures.len() {
+            return Err(SanitizeError::IndexOutOfBounds);
+        }
+
+        // every signature must belong to a static account key
+        if self.signatures.len() > self.message.static_account_keys().len() {
+            return Err(SanitizeError::IndexOutOfBounds);
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_roundtrip_transaction() {
+    use solana_hash::Hash;
+    use solana_message::MessageHeader;
+    use solana_pubkey::Pubkey;
+
+    use crate::v3;
+
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::from([7; 64])],
+        message: VersionedMessage::V3(v3::Message {
+            compute_budget_header: v3::ComputeBudgetHeader::new(Some(1), None, None, None),
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 0,
+            },
+            account_keys: vec![Pubkey::new_unique()],
+            recent_blockhash: Hash::new_unique(),
+            instructions: vec![],
+            address_table_lookups: vec![],
+        }),
+    };
+
+    let bytes = transaction.serialize();
+    assert_eq!(bytes[0], 1);
+    assert_eq!(bytes[65], 0x83);
+    assert_eq!(VersionedTransaction::parse(&bytes), Ok(transaction.clone()));
+
+    let mut unsigned = transaction;
+    unsigned.signatures.clear();
+    assert_eq!(
+        VersionedTransaction::parse(&unsigned.serialize()),
+        Err(ParseError::Sanitize(SanitizeError::IndexOutOfBounds))
+    );
+}
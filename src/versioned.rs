@@ -0,0 +1,728 @@
+//! Versioned wrapper over every message format this crate knows about.
+//!
+//! The candidate formats reuse the `VersionedMessage` prefix scheme: if the
+//! first bit of the first byte is set, the remaining 7 bits select the message
+//! version. `v0` keeps its existing version number and the candidate
+//! versions `v1` to `v5` take the next free ones. `v3b`, the v3 fields with
+//! the compute budget header moved to the end, is version 6.
+
+use core::fmt;
+use std::collections::HashSet;
+
+use bincode::Options;
+use serde::{
+    de::{self, Deserializer, SeqAccess, Unexpected, Visitor},
+    ser::{SerializeTuple, Serializer},
+};
+use serde_derive::{Deserialize, Serialize};
+use solana_hash::Hash;
+use solana_message::{
+    MESSAGE_VERSION_PREFIX, MessageHeader, compiled_instruction::CompiledInstruction, legacy, v0,
+};
+use solana_packet::PACKET_DATA_SIZE;
+use solana_pubkey::Pubkey;
+use solana_sanitize::{Sanitize, SanitizeError};
+
+use solana_transaction_error::TransactionError;
+
+#[cfg(feature = "v3b")]
+use crate::v3b;
+use crate::{
+    compute_budget::{ComputeBudgetHeaderFields, ComputeBudgetRequest},
+    v1, v2, v3, v4, v5,
+};
+
+/// Either a legacy, v0 or one of the candidate compute budget messages.
+///
+/// Non-exhaustive since the `v3b` feature adds a variant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VersionedMessage {
+    Legacy(legacy::Message),
+    V0(v0::Message),
+    V1(v1::Message),
+    V2(v2::Message),
+    V3(v3::Message),
+    V4(v4::Message),
+    V5(v5::Message),
+    #[cfg(feature = "v3b")]
+    V3b(v3b::Message),
+}
+
+/// The message formats understood by [`VersionedMessage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum MessageVersion {
+    Legacy,
+    V0,
+    V1,
+    V2,
+    V3,
+    V4,
+    V5,
+    #[cfg(feature = "v3b")]
+    V3b,
+}
+
+impl MessageVersion {
+    pub const ALL: &'static [MessageVersion] = &[
+        MessageVersion::Legacy,
+        MessageVersion::V0,
+        MessageVersion::V1,
+        MessageVersion::V2,
+        MessageVersion::V3,
+        MessageVersion::V4,
+        MessageVersion::V5,
+        #[cfg(feature = "v3b")]
+        MessageVersion::V3b,
+    ];
+
+    /// The version number encoded in the prefix byte, or `None` for legacy.
+    pub fn number(self) -> Option<u8> {
+        match self {
+            MessageVersion::Legacy => None,
+            MessageVersion::V0 => Some(0),
+            MessageVersion::V1 => Some(1),
+            MessageVersion::V2 => Some(2),
+            MessageVersion::V3 => Some(3),
+            MessageVersion::V4 => Some(4),
+            MessageVersion::V5 => Some(5),
+            #[cfg(feature = "v3b")]
+            MessageVersion::V3b => Some(6),
+        }
+    }
+}
+
+impl fmt::Display for MessageVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self, self.number()) {
+            #[cfg(feature = "v3b")]
+            (MessageVersion::V3b, _) => f.write_str("v3b"),
+            (_, Some(number)) => write!(f, "v{number}"),
+            (_, None) => f.write_str("legacy"),
+        }
+    }
+}
+
+impl core::str::FromStr for MessageVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MessageVersion::ALL
+            .iter()
+            .copied()
+            .find(|version| version.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown message version: {s}"))
+    }
+}
+
+/// Errors returned when decoding and validating a serialized message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The bytes are not a valid encoding of the message they claim to be.
+    InvalidEncoding,
+    /// The version prefix names a message version this crate does not know.
+    UnsupportedVersion(u8),
+    /// The message decoded but failed sanitization.
+    Sanitize(SanitizeError),
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidEncoding => f.write_str("invalid message encoding"),
+            ParseError::UnsupportedVersion(version) => {
+                write!(f, "unsupported message version: {version}")
+            }
+            ParseError::Sanitize(err) => write!(f, "message failed sanitization: {err}"),
+        }
+    }
+}
+
+impl From<SanitizeError> for ParseError {
+    fn from(err: SanitizeError) -> Self {
+        ParseError::Sanitize(err)
+    }
+}
+
+/// The bincode configuration used on the wire: fixed-width integers, no
+/// trailing bytes and no more than a packet's worth of data.
+pub(crate) fn wire_options() -> impl Options {
+    bincode::options()
+        .with_limit(PACKET_DATA_SIZE as u64)
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+}
+
+impl VersionedMessage {
+    pub fn version(&self) -> MessageVersion {
+        match self {
+            Self::Legacy(_) => MessageVersion::Legacy,
+            Self::V0(_) => MessageVersion::V0,
+            Self::V1(_) => MessageVersion::V1,
+            Self::V2(_) => MessageVersion::V2,
+            Self::V3(_) => MessageVersion::V3,
+            Self::V4(_) => MessageVersion::V4,
+            Self::V5(_) => MessageVersion::V5,
+            #[cfg(feature = "v3b")]
+            Self::V3b(_) => MessageVersion::V3b,
+        }
+    }
+
+    /// Decode a message from its wire bytes without sanitizing it.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, ParseError> {
+        let prefix = *bytes.first().ok_or(ParseError::InvalidEncoding)?;
+        if prefix & MESSAGE_VERSION_PREFIX != 0 {
+            let version = prefix & !MESSAGE_VERSION_PREFIX;
+            if !MessageVersion::ALL
+                .iter()
+                .any(|known| known.number() == Some(version))
+            {
+                return Err(ParseError::UnsupportedVersion(version));
+            }
+        }
+        wire_options()
+            .deserialize(bytes)
+            .map_err(|_| ParseError::InvalidEncoding)
+    }
+
+    /// Decode and sanitize a message from its wire bytes.
+    pub fn parse(bytes: &[u8]) -> Result<Self, ParseError> {
+        let message = Self::deserialize(bytes)?;
+        message.sanitize()?;
+        Ok(message)
+    }
+
+    /// Serialize the message, including its version prefix.
+    pub fn serialize(&self) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
+    }
+
+    /// The bytes a signer signs: the same as [`Self::serialize`], so the
+    /// version prefix is covered by every signature.
+    pub fn serialize_for_signing(&self) -> Vec<u8> {
+        self.serialize()
+    }
+
+    /// Blake3 hash of the serialized message, as used to deduplicate
+    /// transactions in the status cache.
+    pub fn hash(&self) -> Hash {
+        Self::hash_raw_message(&self.serialize())
+    }
+
+    /// Blake3 hash of serialized message bytes, domain separated the same way
+    /// as `solana_message::VersionedMessage::hash_raw_message` so every
+    /// version shares one keyspace.
+    pub fn hash_raw_message(message_bytes: &[u8]) -> Hash {
+        solana_message::VersionedMessage::hash_raw_message(message_bytes)
+    }
+
+    pub fn sanitize(&self) -> Result<(), SanitizeError> {
+        match self {
+            Self::Legacy(message) => message.sanitize(),
+            Self::V0(message) => message.sanitize(),
+            Self::V1(message) => message.sanitize(),
+            Self::V2(message) => message.sanitize(),
+            Self::V3(message) => message.sanitize(),
+            Self::V4(message) => message.sanitize(),
+            Self::V5(message) => message.sanitize(),
+            #[cfg(feature = "v3b")]
+            Self::V3b(message) => message.sanitize(),
+        }
+    }
+
+    pub fn header(&self) -> MessageHeader {
+        match self {
+            Self::Legacy(message) => message.header,
+            Self::V0(message) => message.header,
+            Self::V1(message) => message.header.as_message_header(),
+            Self::V2(message) => message.header.as_message_header(),
+            Self::V3(message) => message.header,
+            Self::V4(message) => message.header,
+            Self::V5(message) => message.header,
+            #[cfg(feature = "v3b")]
+            Self::V3b(message) => message.header,
+        }
+    }
+
+    pub fn static_account_keys(&self) -> &[Pubkey] {
+        match self {
+            Self::Legacy(message) => &message.account_keys,
+            Self::V0(message) => &message.account_keys,
+            Self::V1(message) => &message.account_keys,
+            Self::V2(message) => &message.account_keys,
+            Self::V3(message) => &message.account_keys,
+            Self::V4(message) => &message.account_keys,
+            Self::V5(message) => &message.account_keys,
+            #[cfg(feature = "v3b")]
+            Self::V3b(message) => &message.account_keys,
+        }
+    }
+
+    /// Whether the account at `key_index` must sign the message.
+    pub fn is_signer(&self, key_index: usize) -> bool {
+        key_index < usize::from(self.header().num_required_signatures)
+    }
+
+    /// Whether the account at `key_index` is invoked by any instruction.
+    pub fn is_key_called_as_program(&self, key_index: usize) -> bool {
+        match self {
+            Self::Legacy(message) => message.is_key_called_as_program(key_index),
+            Self::V0(message) => message.is_key_called_as_program(key_index),
+            Self::V1(message) => message.is_key_called_as_program(key_index),
+            Self::V2(message) => message.is_key_called_as_program(key_index),
+            Self::V3(message) => message.is_key_called_as_program(key_index),
+            Self::V4(message) => message.is_key_called_as_program(key_index),
+            Self::V5(message) => message.is_key_called_as_program(key_index),
+            #[cfg(feature = "v3b")]
+            Self::V3b(message) => message.is_key_called_as_program(key_index),
+        }
+    }
+
+    /// Whether the account at `key_index` may be write locked, using each
+    /// version's own demotion rules.
+    pub fn is_maybe_writable(
+        &self,
+        key_index: usize,
+        reserved_account_keys: Option<&HashSet<Pubkey>>,
+    ) -> bool {
+        match self {
+            Self::Legacy(message) => message.is_maybe_writable(key_index, reserved_account_keys),
+            Self::V0(message) => message.is_maybe_writable(key_index, reserved_account_keys),
+            Self::V1(message) => message.is_maybe_writable(key_index, reserved_account_keys),
+            Self::V2(message) => message.is_maybe_writable(key_index, reserved_account_keys),
+            Self::V3(message) => message.is_maybe_writable(key_index, reserved_account_keys),
+            Self::V4(message) => message.is_maybe_writable(key_index, reserved_account_keys),
+            Self::V5(message) => message.is_maybe_writable(key_index, reserved_account_keys),
+            #[cfg(feature = "v3b")]
+            Self::V3b(message) => message.is_maybe_writable(key_index, reserved_account_keys),
+        }
+    }
+
+    pub fn address_table_lookups(&self) -> Option<&[v0::MessageAddressTableLookup]> {
+        match self {
+            Self::Legacy(_) => None,
+            Self::V0(message) => Some(&message.address_table_lookups),
+            Self::V1(message) => Some(&message.address_table_lookups),
+            Self::V2(message) => Some(&message.address_table_lookups),
+            Self::V3(message) => Some(&message.address_table_lookups),
+            Self::V4(message) => Some(&message.address_table_lookups),
+            Self::V5(message) => Some(&message.address_table_lookups),
+            #[cfg(feature = "v3b")]
+            Self::V3b(message) => Some(&message.address_table_lookups),
+        }
+    }
+
+    pub fn recent_blockhash(&self) -> &Hash {
+        match self {
+            Self::Legacy(message) => &message.recent_blockhash,
+            Self::V0(message) => &message.recent_blockhash,
+            Self::V1(message) => &message.recent_blockhash,
+            Self::V2(message) => &message.recent_blockhash,
+            Self::V3(message) => &message.recent_blockhash,
+            Self::V4(message) => &message.recent_blockhash,
+            Self::V5(message) => &message.recent_blockhash,
+            #[cfg(feature = "v3b")]
+            Self::V3b(message) => &message.recent_blockhash,
+        }
+    }
+
+    pub fn instructions(&self) -> &[CompiledInstruction] {
+        match self {
+            Self::Legacy(message) => &message.instructions,
+            Self::V0(message) => &message.instructions,
+            Self::V1(message) => &message.instructions,
+            Self::V2(message) => &message.instructions,
+            Self::V3(message) => &message.instructions,
+            Self::V4(message) => &message.instructions,
+            Self::V5(message) => &message.instructions,
+            #[cfg(feature = "v3b")]
+            Self::V3b(message) => &message.instructions,
+        }
+    }
+
+    /// The v3, v4 or v5 style compute budget header, for the versions that
+    /// carry one.
+    pub fn compute_budget_header(&self) -> Option<&dyn ComputeBudgetHeaderFields> {
+        match self {
+            Self::Legacy(_) | Self::V0(_) | Self::V1(_) | Self::V2(_) => None,
+            Self::V3(message) => Some(&message.compute_budget_header),
+            Self::V4(message) => Some(&message.compute_budget_header),
+            Self::V5(message) => Some(&message.compute_budget_header),
+            #[cfg(feature = "v3b")]
+            Self::V3b(message) => Some(&message.compute_budget_header),
+        }
+    }
+
+    /// The compute budget requested by this message, from its header and/or
+    /// its compute budget program instructions.
+    pub fn compute_budget_request(&self) -> Result<ComputeBudgetRequest, TransactionError> {
+        match self {
+            Self::Legacy(message) => ComputeBudgetRequest::default()
+                .process_instructions(&message.account_keys, &message.instructions),
+            Self::V0(message) => ComputeBudgetRequest::default()
+                .process_instructions(&message.account_keys, &message.instructions),
+            Self::V1(message) => message.compute_budget_request(),
+            Self::V2(message) => message.compute_budget_request(),
+            Self::V3(message) => message.compute_budget_request(),
+            Self::V4(message) => message.compute_budget_request(),
+            Self::V5(message) => message.compute_budget_request(),
+            #[cfg(feature = "v3b")]
+            Self::V3b(message) => message.compute_budget_request(),
+        }
+    }
+}
+
+/// The version prefix followed by `message`, as it appears on the wire.
+fn serialize_with_prefix<T: serde::Serialize>(version: MessageVersion, message: &T) -> Vec<u8> {
+    let prefix = MESSAGE_VERSION_PREFIX | version.number().unwrap();
+    bincode::serialize(&(prefix, message)).unwrap()
+}
+
+macro_rules! impl_signing {
+    ($($module:ident => $version:ident),*) => {$(
+        impl $module::Message {
+            /// The bytes a signer signs: the version prefix followed by the message.
+            pub fn serialize_for_signing(&self) -> Vec<u8> {
+                serialize_with_prefix(MessageVersion::$version, self)
+            }
+
+            /// Blake3 hash of the serialized message, including the version prefix.
+            pub fn hash(&self) -> Hash {
+                Self::hash_raw_message(&self.serialize_for_signing())
+            }
+
+            /// Blake3 hash of serialized message bytes, see
+            /// [`VersionedMessage::hash_raw_message`].
+            pub fn hash_raw_message(message_bytes: &[u8]) -> Hash {
+                VersionedMessage::hash_raw_message(message_bytes)
+            }
+        }
+    )*};
+}
+
+impl_signing!(v1 => V1, v2 => V2, v3 => V3, v4 => V4, v5 => V5);
+#[cfg(feature = "v3b")]
+impl_signing!(v3b => V3b);
+
+impl serde::Serialize for VersionedMessage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let Some(version) = self.version().number() else {
+            let Self::Legacy(message) = self else {
+                unreachable!()
+            };
+            let mut seq = serializer.serialize_tuple(1)?;
+            seq.serialize_element(message)?;
+            return seq.end();
+        };
+
+        let mut seq = serializer.serialize_tuple(2)?;
+        seq.serialize_element(&(MESSAGE_VERSION_PREFIX | version))?;
+        match self {
+            Self::Legacy(_) => unreachable!(),
+            Self::V0(message) => seq.serialize_element(message)?,
+            Self::V1(message) => seq.serialize_element(message)?,
+            Self::V2(message) => seq.serialize_element(message)?,
+            Self::V3(message) => seq.serialize_element(message)?,
+            Self::V4(message) => seq.serialize_element(message)?,
+            Self::V5(message) => seq.serialize_element(message)?,
+            #[cfg(feature = "v3b")]
+            Self::V3b(message) => seq.serialize_element(message)?,
+        }
+        seq.end()
+    }
+}
+
+enum MessagePrefix {
+    Legacy(u8),
+    Versioned(u8),
+}
+
+impl<'de> serde::Deserialize<'de> for MessagePrefix {
+    fn deserialize<D>(deserializer: D) -> Result<MessagePrefix, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct PrefixVisitor;
+
+        impl Visitor<'_> for PrefixVisitor {
+            type Value = MessagePrefix;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("message prefix byte")
+            }
+
+            // serde_json calls visit_u64 for unsigned integers
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<MessagePrefix, E> {
+                if value > u8::MAX as u64 {
+                    Err(de::Error::invalid_type(Unexpected::Unsigned(value), &self))?;
+                }
+
+                let byte = value as u8;
+                if byte & MESSAGE_VERSION_PREFIX != 0 {
+                    Ok(MessagePrefix::Versioned(byte & !MESSAGE_VERSION_PREFIX))
+                } else {
+                    Ok(MessagePrefix::Legacy(byte))
+                }
+            }
+        }
+
+        deserializer.deserialize_u8(PrefixVisitor)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for VersionedMessage {
+    fn deserialize<D>(deserializer: D) -> Result<VersionedMessage, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct MessageVisitor;
+
+        impl<'de> Visitor<'de> for MessageVisitor {
+            type Value = VersionedMessage;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("message bytes")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<VersionedMessage, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let prefix: MessagePrefix = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+
+                match prefix {
+                    MessagePrefix::Legacy(num_required_signatures) => {
+                        // The remaining fields of the legacy Message struct after the first byte.
+                        #[derive(Serialize, Deserialize)]
+                        struct RemainingLegacyMessage {
+                            pub num_readonly_signed_accounts: u8,
+                            pub num_readonly_unsigned_accounts: u8,
+                            #[serde(with = "solana_short_vec")]
+                            pub account_keys: Vec<Pubkey>,
+                            pub recent_blockhash: Hash,
+                            #[serde(with = "solana_short_vec")]
+                            pub instructions: Vec<CompiledInstruction>,
+                        }
+
+                        let message: RemainingLegacyMessage = seq
+                            .next_element()?
+                            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+
+                        Ok(VersionedMessage::Legacy(legacy::Message {
+                            header: MessageHeader {
+                                num_required_signatures,
+                                num_readonly_signed_accounts: message.num_readonly_signed_accounts,
+                                num_readonly_unsigned_accounts: message
+                                    .num_readonly_unsigned_accounts,
+                            },
+                            account_keys: message.account_keys,
+                            recent_blockhash: message.recent_blockhash,
+                            instructions: message.instructions,
+                        }))
+                    }
+                    MessagePrefix::Versioned(version) => {
+                        let missing = || de::Error::invalid_length(1, &self);
+                        match version {
+                            0 => Ok(VersionedMessage::V0(
+                                seq.next_element()?.ok_or_else(missing)?,
+                            )),
+                            1 => Ok(VersionedMessage::V1(
+                                seq.next_element()?.ok_or_else(missing)?,
+                            )),
+                            2 => Ok(VersionedMessage::V2(
+                                seq.next_element()?.ok_or_else(missing)?,
+                            )),
+                            3 => Ok(VersionedMessage::V3(
+                                seq.next_element()?.ok_or_else(missing)?,
+                            )),
+                            4 => Ok(VersionedMessage::V4(
+                                seq.next_element()?.ok_or_else(missing)?,
+                            )),
+                            5 => Ok(VersionedMessage::V5(
+                                seq.next_element()?.ok_or_else(missing)?,
+                            )),
+                            #[cfg(feature = "v3b")]
+                            6 => Ok(VersionedMessage::V3b(
+                                seq.next_element()?.ok_or_else(missing)?,
+                            )),
+                            127 => {
+                                // 0xff is the first byte of off-chain messages
+                                Err(de::Error::custom("off-chain messages are not accepted"))
+                            }
+                            _ => Err(de::Error::invalid_value(
+                                de::Unexpected::Unsigned(version as u64),
+                                &"a valid transaction message version",
+                            )),
+                        }
+                    }
+                }
+            }
+        }
+
+        deserializer.deserialize_tuple(2, MessageVisitor)
+    }
+}
+
+#[test]
+fn test_roundtrip_versioned_all() {
+    use solana_message::MessageHeader;
+
+    let header = MessageHeader {
+        num_required_signatures: 1,
+        num_readonly_signed_accounts: 0,
+        num_readonly_unsigned_accounts: 1,
+    };
+    let account_keys = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+    let recent_blockhash = Hash::new_unique();
+    let instructions = vec![CompiledInstruction::new_from_raw_parts(
+        1,
+        vec![1, 2, 3],
+        vec![0],
+    )];
+
+    let messages = [
+        VersionedMessage::Legacy(legacy::Message {
+            header,
+            account_keys: account_keys.clone(),
+            recent_blockhash,
+            instructions: instructions.clone(),
+        }),
+        VersionedMessage::V0(v0::Message {
+            header,
+            account_keys: account_keys.clone(),
+            recent_blockhash,
+            instructions: instructions.clone(),
+            address_table_lookups: vec![],
+        }),
+        VersionedMessage::V1(v1::Message {
+            header: v1::MessageHeader {
+                compute_unit_price: 12,
+                compute_unit_limit: 34,
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 1,
+            },
+            account_keys: account_keys.clone(),
+            recent_blockhash,
+            instructions: instructions.clone(),
+            address_table_lookups: vec![],
+        }),
+        VersionedMessage::V2(v2::Message {
+            header: v2::MessageHeader {
+                compute_unit_price: 12,
+                compute_unit_limit: 34,
+                loaded_accounts_data_limit: 56,
+                requested_heap_bytes: 78,
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 1,
+            },
+            account_keys: account_keys.clone(),
+            recent_blockhash,
+            instructions: instructions.clone(),
+            address_table_lookups: vec![],
+        }),
+        VersionedMessage::V3(v3::Message {
+            compute_budget_header: v3::ComputeBudgetHeader::new(Some(34), Some(12), None, None),
+            header,
+            account_keys: account_keys.clone(),
+            recent_blockhash,
+            instructions: instructions.clone(),
+            address_table_lookups: vec![],
+        }),
+        VersionedMessage::V4(v4::Message {
+            compute_budget_header: v4::ComputeBudgetHeader::new(Some(34), Some(12), None, None),
+            header,
+            account_keys: account_keys.clone(),
+            recent_blockhash,
+            instructions: instructions.clone(),
+            address_table_lookups: vec![],
+        }),
+        VersionedMessage::V5(v5::Message {
+            compute_budget_header: v5::ComputeBudgetHeader::new(
+                Some(34),
+                Some(v5::ScaledPrice::new(3, 12).unwrap()),
+                None,
+                None,
+            ),
+            header,
+            account_keys: account_keys.clone(),
+            recent_blockhash,
+            instructions: instructions.clone(),
+            address_table_lookups: vec![],
+        }),
+        #[cfg(feature = "v3b")]
+        VersionedMessage::V3b(v3b::Message {
+            header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+            address_table_lookups: vec![],
+            compute_budget_header: v3::ComputeBudgetHeader::new(Some(34), Some(12), None, None),
+        }),
+    ];
+
+    for (message, expected_prefix) in messages.iter().zip([
+        None,
+        Some(0x80),
+        Some(0x81),
+        Some(0x82),
+        Some(0x83),
+        Some(0x84),
+        Some(0x85),
+        #[cfg(feature = "v3b")]
+        Some(0x86),
+    ]) {
+        let bytes = message.serialize();
+        if let Some(expected_prefix) = expected_prefix {
+            assert_eq!(bytes[0], expected_prefix);
+        } else {
+            assert_eq!(bytes[0], 1);
+        }
+        assert_eq!(VersionedMessage::parse(&bytes).unwrap(), *message);
+
+        // trailing bytes are not part of the message
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert_eq!(
+            VersionedMessage::parse(&padded),
+            Err(ParseError::InvalidEncoding)
+        );
+
+        assert_eq!(message.serialize_for_signing(), bytes);
+        assert_eq!(message.hash(), VersionedMessage::hash_raw_message(&bytes));
+    }
+
+    assert_eq!(
+        VersionedMessage::parse(&[0x87]),
+        Err(ParseError::UnsupportedVersion(7))
+    );
+
+    // legacy and v0 hash exactly as the SDK does, so every version can share
+    // one status cache
+    for message in &messages[..2] {
+        let sdk_message = match message {
+            VersionedMessage::Legacy(message) => {
+                solana_message::VersionedMessage::Legacy(message.clone())
+            }
+            VersionedMessage::V0(message) => solana_message::VersionedMessage::V0(message.clone()),
+            _ => unreachable!(),
+        };
+        let sdk_hash = solana_message::VersionedMessage::hash_raw_message(
+            &bincode::serialize(&sdk_message).unwrap(),
+        );
+        assert_eq!(message.hash(), sdk_hash);
+    }
+
+    // the same message in different versions hashes to different keys
+    let hashes: HashSet<Hash> = messages.iter().map(|m| m.hash()).collect();
+    assert_eq!(hashes.len(), messages.len());
+}
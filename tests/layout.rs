@@ -0,0 +1,348 @@
+//! Every encoder against the layout spec in `v1tx::layout`, and the generated
+//! tables in `docs/layout.md`.
+//!
+//! Run with `V1TX_BLESS=1` to regenerate the tables after an intentional
+//! layout change, with the `v3b` feature on since they cover every version.
+
+use solana_hash::Hash;
+use solana_message::{MessageHeader, legacy, v0};
+use solana_pubkey::Pubkey;
+#[cfg(feature = "v3b")]
+use v1tx::v3b;
+use v1tx::{layout::MessageLayout, v1, v2, v3, v4, v5, versioned::VersionedMessage};
+
+const DOC_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/docs/layout.md");
+
+// distinct values, so a field read from the wrong offset can't match
+const COMPUTE_UNIT_LIMIT: u32 = 0x0102_0304;
+const COMPUTE_UNIT_PRICE: u64 = 0x1112_1314_1516_1718;
+const LOADED_ACCOUNTS_DATA_LIMIT: u32 = 0x2122_2324;
+const REQUESTED_HEAP_BYTES: u32 = 0x3132_3334;
+const MAX_FEE_LAMPORTS: u64 = 0x4142_4344_4546_4748;
+const HEADER: MessageHeader = MessageHeader {
+    num_required_signatures: 3,
+    num_readonly_signed_accounts: 2,
+    num_readonly_unsigned_accounts: 1,
+};
+
+fn varint(mut value: u64) -> Vec<u8> {
+    let mut bytes = vec![];
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+    bytes
+}
+
+type Fields = Vec<(&'static str, Vec<u8>)>;
+
+/// Each version with every budget field set, and the bytes each field should
+/// encode to.
+fn messages() -> Vec<(VersionedMessage, Fields)> {
+    let account_keys = vec![Pubkey::new_unique(); 4];
+    let recent_blockhash = Hash::new_unique();
+    let counters = [
+        (
+            "num_required_signatures",
+            vec![HEADER.num_required_signatures],
+        ),
+        (
+            "num_readonly_signed_accounts",
+            vec![HEADER.num_readonly_signed_accounts],
+        ),
+        (
+            "num_readonly_unsigned_accounts",
+            vec![HEADER.num_readonly_unsigned_accounts],
+        ),
+    ];
+    let with_counters = |fields: Fields| fields.into_iter().chain(counters.clone()).collect();
+    let scaled_price = v5::ScaledPrice::new(3, 0x0102_0305).unwrap();
+    let v0 = v0::Message {
+        header: HEADER,
+        account_keys: account_keys.clone(),
+        recent_blockhash,
+        instructions: vec![],
+        address_table_lookups: vec![],
+    };
+    // v3b's body is everything in v0 after the prefix and counters
+    #[cfg(feature = "v3b")]
+    let body = VersionedMessage::V0(v0.clone()).serialize()[4..].to_vec();
+
+    #[allow(unused_mut)]
+    let mut messages = vec![
+        (
+            VersionedMessage::Legacy(legacy::Message {
+                header: HEADER,
+                account_keys: account_keys.clone(),
+                recent_blockhash,
+                instructions: vec![],
+            }),
+            with_counters(vec![]),
+        ),
+        (
+            VersionedMessage::V0(v0),
+            with_counters(vec![("prefix", vec![0x80])]),
+        ),
+        (
+            VersionedMessage::V1(v1::Message {
+                header: v1::MessageHeader {
+                    compute_unit_price: COMPUTE_UNIT_PRICE,
+                    compute_unit_limit: COMPUTE_UNIT_LIMIT,
+                    num_required_signatures: HEADER.num_required_signatures,
+                    num_readonly_signed_accounts: HEADER.num_readonly_signed_accounts,
+                    num_readonly_unsigned_accounts: HEADER.num_readonly_unsigned_accounts,
+                },
+                account_keys: account_keys.clone(),
+                recent_blockhash,
+                instructions: vec![],
+                address_table_lookups: vec![],
+            }),
+            with_counters(vec![
+                ("prefix", vec![0x81]),
+                (
+                    "compute_unit_price",
+                    COMPUTE_UNIT_PRICE.to_le_bytes().to_vec(),
+                ),
+                (
+                    "compute_unit_limit",
+                    COMPUTE_UNIT_LIMIT.to_le_bytes().to_vec(),
+                ),
+            ]),
+        ),
+        (
+            VersionedMessage::V2(v2::Message {
+                header: v2::MessageHeader {
+                    compute_unit_price: COMPUTE_UNIT_PRICE,
+                    compute_unit_limit: COMPUTE_UNIT_LIMIT,
+                    loaded_accounts_data_limit: LOADED_ACCOUNTS_DATA_LIMIT,
+                    requested_heap_bytes: REQUESTED_HEAP_BYTES,
+                    num_required_signatures: HEADER.num_required_signatures,
+                    num_readonly_signed_accounts: HEADER.num_readonly_signed_accounts,
+                    num_readonly_unsigned_accounts: HEADER.num_readonly_unsigned_accounts,
+                },
+                account_keys: account_keys.clone(),
+                recent_blockhash,
+                instructions: vec![],
+                address_table_lookups: vec![],
+            }),
+            with_counters(vec![
+                ("prefix", vec![0x82]),
+                (
+                    "compute_unit_price",
+                    COMPUTE_UNIT_PRICE.to_le_bytes().to_vec(),
+                ),
+                (
+                    "compute_unit_limit",
+                    COMPUTE_UNIT_LIMIT.to_le_bytes().to_vec(),
+                ),
+                (
+                    "loaded_accounts_data_limit",
+                    LOADED_ACCOUNTS_DATA_LIMIT.to_le_bytes().to_vec(),
+                ),
+                (
+                    "requested_heap_bytes",
+                    REQUESTED_HEAP_BYTES.to_le_bytes().to_vec(),
+                ),
+            ]),
+        ),
+        (
+            VersionedMessage::V3(v3::Message {
+                compute_budget_header: v3::ComputeBudgetHeader::new(
+                    Some(COMPUTE_UNIT_LIMIT),
+                    Some(COMPUTE_UNIT_PRICE),
+                    Some(LOADED_ACCOUNTS_DATA_LIMIT),
+                    Some(REQUESTED_HEAP_BYTES),
+                )
+                .with_max_fee_lamports(Some(MAX_FEE_LAMPORTS)),
+                header: HEADER,
+                account_keys: account_keys.clone(),
+                recent_blockhash,
+                instructions: vec![],
+                address_table_lookups: vec![],
+            }),
+            with_counters(vec![
+                ("prefix", vec![0x83]),
+                ("flags", vec![0b11111]),
+                (
+                    "compute_unit_limit",
+                    COMPUTE_UNIT_LIMIT.to_le_bytes().to_vec(),
+                ),
+                (
+                    "compute_unit_price",
+                    COMPUTE_UNIT_PRICE.to_le_bytes().to_vec(),
+                ),
+                (
+                    "loaded_accounts_data_limit",
+                    LOADED_ACCOUNTS_DATA_LIMIT.to_le_bytes().to_vec(),
+                ),
+                (
+                    "requested_heap_bytes_limit",
+                    REQUESTED_HEAP_BYTES.to_le_bytes().to_vec(),
+                ),
+                ("max_fee_lamports", MAX_FEE_LAMPORTS.to_le_bytes().to_vec()),
+            ]),
+        ),
+        (
+            VersionedMessage::V4(v4::Message {
+                compute_budget_header: v4::ComputeBudgetHeader::new(
+                    Some(COMPUTE_UNIT_LIMIT),
+                    Some(COMPUTE_UNIT_PRICE),
+                    Some(LOADED_ACCOUNTS_DATA_LIMIT),
+                    Some(REQUESTED_HEAP_BYTES),
+                )
+                .with_max_fee_lamports(Some(MAX_FEE_LAMPORTS)),
+                header: HEADER,
+                account_keys: account_keys.clone(),
+                recent_blockhash,
+                instructions: vec![],
+                address_table_lookups: vec![],
+            }),
+            with_counters(vec![
+                ("prefix", vec![0x84]),
+                ("flags", vec![0b11111]),
+                ("compute_unit_limit", varint(COMPUTE_UNIT_LIMIT.into())),
+                ("compute_unit_price", varint(COMPUTE_UNIT_PRICE)),
+                (
+                    "loaded_accounts_data_limit",
+                    varint(LOADED_ACCOUNTS_DATA_LIMIT.into()),
+                ),
+                (
+                    "requested_heap_bytes_limit",
+                    varint(REQUESTED_HEAP_BYTES.into()),
+                ),
+                ("max_fee_lamports", varint(MAX_FEE_LAMPORTS)),
+            ]),
+        ),
+        (
+            VersionedMessage::V5(v5::Message {
+                compute_budget_header: v5::ComputeBudgetHeader::new(
+                    Some(COMPUTE_UNIT_LIMIT),
+                    Some(scaled_price),
+                    Some(LOADED_ACCOUNTS_DATA_LIMIT),
+                    Some(REQUESTED_HEAP_BYTES),
+                )
+                .with_max_fee_lamports(Some(MAX_FEE_LAMPORTS)),
+                header: HEADER,
+                account_keys: account_keys.clone(),
+                recent_blockhash,
+                instructions: vec![],
+                address_table_lookups: vec![],
+            }),
+            with_counters(vec![
+                ("prefix", vec![0x85]),
+                ("flags", vec![0b11111]),
+                (
+                    "compute_unit_limit",
+                    COMPUTE_UNIT_LIMIT.to_le_bytes().to_vec(),
+                ),
+                ("compute_unit_price.exponent", vec![scaled_price.exponent()]),
+                (
+                    "compute_unit_price.mantissa",
+                    scaled_price.mantissa().to_le_bytes().to_vec(),
+                ),
+                (
+                    "loaded_accounts_data_limit",
+                    LOADED_ACCOUNTS_DATA_LIMIT.to_le_bytes().to_vec(),
+                ),
+                (
+                    "requested_heap_bytes_limit",
+                    REQUESTED_HEAP_BYTES.to_le_bytes().to_vec(),
+                ),
+                ("max_fee_lamports", MAX_FEE_LAMPORTS.to_le_bytes().to_vec()),
+            ]),
+        ),
+    ];
+    #[cfg(feature = "v3b")]
+    messages.push((
+        VersionedMessage::V3b(v3b::Message {
+            header: HEADER,
+            account_keys,
+            recent_blockhash,
+            instructions: vec![],
+            address_table_lookups: vec![],
+            compute_budget_header: v3::ComputeBudgetHeader::new(
+                Some(COMPUTE_UNIT_LIMIT),
+                Some(COMPUTE_UNIT_PRICE),
+                Some(LOADED_ACCOUNTS_DATA_LIMIT),
+                Some(REQUESTED_HEAP_BYTES),
+            )
+            .with_max_fee_lamports(Some(MAX_FEE_LAMPORTS)),
+        }),
+        [("prefix", vec![0x86])]
+            .into_iter()
+            .chain(counters.clone())
+            .chain([
+                ("body", body),
+                ("flags", vec![0b11111]),
+                (
+                    "compute_unit_limit",
+                    COMPUTE_UNIT_LIMIT.to_le_bytes().to_vec(),
+                ),
+                (
+                    "compute_unit_price",
+                    COMPUTE_UNIT_PRICE.to_le_bytes().to_vec(),
+                ),
+                (
+                    "loaded_accounts_data_limit",
+                    LOADED_ACCOUNTS_DATA_LIMIT.to_le_bytes().to_vec(),
+                ),
+                (
+                    "requested_heap_bytes_limit",
+                    REQUESTED_HEAP_BYTES.to_le_bytes().to_vec(),
+                ),
+                ("max_fee_lamports", MAX_FEE_LAMPORTS.to_le_bytes().to_vec()),
+            ])
+            .collect(),
+    ));
+    messages
+}
+
+#[test]
+fn test_encoded_offsets() {
+    let messages = messages();
+    assert_eq!(messages.len(), v1tx::versioned::MessageVersion::ALL.len());
+    for (message, expected) in messages {
+        let bytes = message.serialize();
+        let ranges = MessageLayout::message_field_ranges(&message);
+        let fields: Vec<_> = ranges
+            .iter()
+            .map(|(name, range)| (*name, bytes[range.clone()].to_vec()))
+            .collect();
+        assert_eq!(fields, expected, "{}", message.version());
+
+        // the account keys start right after the counters
+        let end = ranges
+            .iter()
+            .find(|(name, _)| *name == "num_readonly_unsigned_accounts")
+            .unwrap()
+            .1
+            .end;
+        assert_eq!(
+            bytes[end],
+            message.static_account_keys().len() as u8,
+            "{}",
+            message.version()
+        );
+    }
+}
+
+#[test]
+fn test_layout_doc() {
+    let expected = v1tx::layout::markdown();
+    if std::env::var_os("V1TX_BLESS").is_some() {
+        if !cfg!(feature = "v3b") {
+            panic!("bless with the v3b feature on, the file covers every version");
+        }
+        std::fs::write(DOC_PATH, &expected).unwrap();
+    }
+
+    let checked_in = std::fs::read_to_string(DOC_PATH).unwrap();
+    // v3b's table comes last, and is only generated with the feature
+    #[cfg(not(feature = "v3b"))]
+    let checked_in = checked_in[..checked_in.find("\n## v3b\n").unwrap()].to_string();
+    assert!(
+        checked_in == expected,
+        "{DOC_PATH} is out of date, rerun with V1TX_BLESS=1 if the layout change is intended"
+    );
+}
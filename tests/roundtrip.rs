@@ -0,0 +1,580 @@
+//! Property-based round trips for the candidate message versions.
+
+use proptest::prelude::*;
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_hash::Hash;
+use solana_message::{
+    MessageHeader,
+    compiled_instruction::CompiledInstruction,
+    v0::{self, MessageAddressTableLookup},
+};
+use solana_pubkey::Pubkey;
+#[cfg(feature = "v3b")]
+use v1tx::v3b;
+use v1tx::{
+    convert::{ConvertError, compute_budget_instruction_indexes},
+    layout::MessageLayout,
+    v1, v2, v3, v4, v5,
+    versioned::{MessageVersion, VersionedMessage},
+};
+
+/// Header counts, keys, instructions and lookups shared by every version.
+#[derive(Debug, Clone)]
+struct MessageParts {
+    header: MessageHeader,
+    account_keys: Vec<Pubkey>,
+    recent_blockhash: Hash,
+    instructions: Vec<CompiledInstruction>,
+    address_table_lookups: Vec<MessageAddressTableLookup>,
+}
+
+fn lookups() -> impl Strategy<Value = Vec<MessageAddressTableLookup>> {
+    prop::collection::vec(
+        (
+            any::<[u8; 32]>(),
+            prop::collection::vec(any::<u8>(), 0..8),
+            prop::collection::vec(any::<u8>(), 0..8),
+        )
+            .prop_filter("lookups load at least one account", |(_, w, r)| {
+                !w.is_empty() || !r.is_empty()
+            })
+            .prop_map(
+                |(key, writable_indexes, readonly_indexes)| MessageAddressTableLookup {
+                    account_key: Pubkey::new_from_array(key),
+                    writable_indexes,
+                    readonly_indexes,
+                },
+            ),
+        0..3,
+    )
+}
+
+fn message_parts() -> impl Strategy<Value = MessageParts> {
+    (
+        prop::collection::hash_set(any::<[u8; 32]>(), 2..16),
+        lookups(),
+        any::<[u8; 32]>(),
+    )
+        .prop_flat_map(|(keys, address_table_lookups, blockhash)| {
+            let num_static = keys.len();
+            let num_dynamic: usize = address_table_lookups
+                .iter()
+                .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+                .sum();
+            let num_total = num_static + num_dynamic;
+
+            let header = (1..num_static).prop_flat_map(move |num_required_signatures| {
+                (
+                    0..num_required_signatures,
+                    0..=num_static - num_required_signatures,
+                )
+                    .prop_map(move |(readonly_signed, readonly_unsigned)| {
+                        MessageHeader {
+                            num_required_signatures: num_required_signatures as u8,
+                            num_readonly_signed_accounts: readonly_signed as u8,
+                            num_readonly_unsigned_accounts: readonly_unsigned as u8,
+                        }
+                    })
+            });
+            let instructions = prop::collection::vec(
+                (
+                    1..num_static as u8,
+                    prop::collection::vec(0..num_total as u8, 0..8),
+                    prop::collection::vec(any::<u8>(), 0..64),
+                )
+                    .prop_map(|(program_id_index, accounts, data)| {
+                        CompiledInstruction::new_from_raw_parts(program_id_index, data, accounts)
+                    }),
+                0..6,
+            );
+
+            let account_keys: Vec<Pubkey> = keys.into_iter().map(Pubkey::new_from_array).collect();
+            (header, instructions).prop_map(move |(header, instructions)| MessageParts {
+                header,
+                account_keys: account_keys.clone(),
+                recent_blockhash: Hash::new_from_array(blockhash),
+                instructions,
+                address_table_lookups: address_table_lookups.clone(),
+            })
+        })
+}
+
+/// Data for at most one of each compute budget instruction, in any order.
+fn compute_budget_instructions() -> impl Strategy<Value = Vec<Vec<u8>>> {
+    (
+        any::<Option<u32>>(),
+        any::<Option<u64>>(),
+        any::<Option<u32>>(),
+        any::<Option<u32>>(),
+    )
+        .prop_map(|(limit, price, loaded, heap)| {
+            [
+                limit.map(ComputeBudgetInstruction::set_compute_unit_limit),
+                price.map(ComputeBudgetInstruction::set_compute_unit_price),
+                loaded.map(ComputeBudgetInstruction::set_loaded_accounts_data_size_limit),
+                heap.map(ComputeBudgetInstruction::request_heap_frame),
+            ]
+            .into_iter()
+            .flatten()
+            .map(|instruction| instruction.data)
+            .collect::<Vec<_>>()
+        })
+        .prop_shuffle()
+}
+
+/// A v0 message that requests its compute budget through instructions, mixed
+/// in with the others, for the fold and unfold paths of the conversions.
+fn v0_message() -> impl Strategy<Value = v0::Message> {
+    (
+        message_parts(),
+        compute_budget_instructions(),
+        prop::collection::vec(any::<prop::sample::Index>(), 4),
+    )
+        .prop_map(|(mut parts, budget_instructions, positions)| {
+            // the program goes last as a readonly unsigned key, so loaded
+            // addresses move up by one
+            let program_id_index = parts.account_keys.len() as u8;
+            parts.account_keys.push(solana_compute_budget_interface::ID);
+            parts.header.num_readonly_unsigned_accounts += 1;
+            for instruction in &mut parts.instructions {
+                for account in &mut instruction.accounts {
+                    if *account >= program_id_index {
+                        *account += 1;
+                    }
+                }
+            }
+            for (data, position) in budget_instructions.into_iter().zip(positions) {
+                let index = position.index(parts.instructions.len() + 1);
+                parts.instructions.insert(
+                    index,
+                    CompiledInstruction::new_from_raw_parts(program_id_index, data, vec![]),
+                );
+            }
+            v0::Message {
+                header: parts.header,
+                account_keys: parts.account_keys,
+                recent_blockhash: parts.recent_blockhash,
+                instructions: parts.instructions,
+                address_table_lookups: parts.address_table_lookups,
+            }
+        })
+}
+
+fn v1_message() -> impl Strategy<Value = v1::Message> {
+    (message_parts(), any::<u64>(), any::<u32>()).prop_map(
+        |(parts, compute_unit_price, compute_unit_limit)| v1::Message {
+            header: v1::MessageHeader {
+                compute_unit_price,
+                compute_unit_limit,
+                num_required_signatures: parts.header.num_required_signatures,
+                num_readonly_signed_accounts: parts.header.num_readonly_signed_accounts,
+                num_readonly_unsigned_accounts: parts.header.num_readonly_unsigned_accounts,
+            },
+            account_keys: parts.account_keys,
+            recent_blockhash: parts.recent_blockhash,
+            instructions: parts.instructions,
+            address_table_lookups: parts.address_table_lookups,
+        },
+    )
+}
+
+fn v2_message() -> impl Strategy<Value = v2::Message> {
+    (
+        message_parts(),
+        any::<u64>(),
+        any::<u32>(),
+        any::<u32>(),
+        any::<u32>(),
+    )
+        .prop_map(
+            |(
+                parts,
+                compute_unit_price,
+                compute_unit_limit,
+                loaded_accounts_data_limit,
+                requested_heap_bytes,
+            )| v2::Message {
+                header: v2::MessageHeader {
+                    compute_unit_price,
+                    compute_unit_limit,
+                    loaded_accounts_data_limit,
+                    requested_heap_bytes,
+                    num_required_signatures: parts.header.num_required_signatures,
+                    num_readonly_signed_accounts: parts.header.num_readonly_signed_accounts,
+                    num_readonly_unsigned_accounts: parts.header.num_readonly_unsigned_accounts,
+                },
+                account_keys: parts.account_keys,
+                recent_blockhash: parts.recent_blockhash,
+                instructions: parts.instructions,
+                address_table_lookups: parts.address_table_lookups,
+            },
+        )
+}
+
+fn v3_message() -> impl Strategy<Value = v3::Message> {
+    (
+        message_parts(),
+        any::<Option<u32>>(),
+        any::<Option<u64>>(),
+        any::<Option<u32>>(),
+        any::<Option<u32>>(),
+        proptest::option::of(any::<u64>()),
+    )
+        .prop_map(
+            |(
+                parts,
+                compute_unit_limit,
+                compute_unit_price,
+                loaded_accounts_data_limit,
+                requested_heap_bytes_limit,
+                max_fee_lamports,
+            )| v3::Message {
+                compute_budget_header: v3::ComputeBudgetHeader::new(
+                    compute_unit_limit,
+                    compute_unit_price,
+                    loaded_accounts_data_limit,
+                    requested_heap_bytes_limit,
+                )
+                .with_max_fee_lamports(max_fee_lamports),
+                header: parts.header,
+                account_keys: parts.account_keys,
+                recent_blockhash: parts.recent_blockhash,
+                instructions: parts.instructions,
+                address_table_lookups: parts.address_table_lookups,
+            },
+        )
+}
+
+#[cfg(feature = "v3b")]
+fn v3b_message() -> impl Strategy<Value = v3b::Message> {
+    v3_message().prop_map(v3b::Message::from)
+}
+
+fn v4_message() -> impl Strategy<Value = v4::Message> {
+    (
+        message_parts(),
+        any::<Option<u32>>(),
+        any::<Option<u64>>(),
+        any::<Option<u32>>(),
+        any::<Option<u32>>(),
+        proptest::option::of(any::<u64>()),
+    )
+        .prop_map(
+            |(
+                parts,
+                compute_unit_limit,
+                compute_unit_price,
+                loaded_accounts_data_limit,
+                requested_heap_bytes_limit,
+                max_fee_lamports,
+            )| v4::Message {
+                compute_budget_header: v4::ComputeBudgetHeader::new(
+                    compute_unit_limit,
+                    compute_unit_price,
+                    loaded_accounts_data_limit,
+                    requested_heap_bytes_limit,
+                )
+                .with_max_fee_lamports(max_fee_lamports),
+                header: parts.header,
+                account_keys: parts.account_keys,
+                recent_blockhash: parts.recent_blockhash,
+                instructions: parts.instructions,
+                address_table_lookups: parts.address_table_lookups,
+            },
+        )
+}
+
+fn v5_message() -> impl Strategy<Value = v5::Message> {
+    let scaled_price = (any::<u8>(), any::<u32>())
+        .prop_filter_map("non-canonical price", |(exponent, mantissa)| {
+            v5::ScaledPrice::new(exponent % 20, mantissa).ok()
+        });
+    (
+        message_parts(),
+        any::<Option<u32>>(),
+        proptest::option::of(scaled_price),
+        any::<Option<u32>>(),
+        any::<Option<u32>>(),
+        proptest::option::of(any::<u64>()),
+    )
+        .prop_map(
+            |(
+                parts,
+                compute_unit_limit,
+                compute_unit_price,
+                loaded_accounts_data_limit,
+                requested_heap_bytes_limit,
+                max_fee_lamports,
+            )| v5::Message {
+                compute_budget_header: v5::ComputeBudgetHeader::new(
+                    compute_unit_limit,
+                    compute_unit_price,
+                    loaded_accounts_data_limit,
+                    requested_heap_bytes_limit,
+                )
+                .with_max_fee_lamports(max_fee_lamports),
+                header: parts.header,
+                account_keys: parts.account_keys,
+                recent_blockhash: parts.recent_blockhash,
+                instructions: parts.instructions,
+                address_table_lookups: parts.address_table_lookups,
+            },
+        )
+}
+
+proptest! {
+    #[test]
+    fn test_roundtrip_v1(message in v1_message()) {
+        message.sanitize().unwrap();
+
+        let bytes = bincode::serialize(&message).unwrap();
+        prop_assert_eq!(bincode::deserialize::<v1::Message>(&bytes).unwrap(), message.clone());
+
+        // everything from the counters on is laid out exactly like v0
+        let counters = MessageLayout::of(MessageVersion::V1)
+            .fixed_offset("num_required_signatures")
+            .unwrap()
+            - 1;
+        let v0 = solana_message::v0::Message {
+            header: message.header.as_message_header(),
+            account_keys: message.account_keys.clone(),
+            recent_blockhash: message.recent_blockhash,
+            instructions: message.instructions.clone(),
+            address_table_lookups: message.address_table_lookups.clone(),
+        };
+        prop_assert_eq!(&bytes[counters..], &bincode::serialize(&v0).unwrap()[..]);
+
+        let versioned = VersionedMessage::V1(message.clone());
+        let versioned_bytes = versioned.serialize();
+        prop_assert_eq!(&versioned_bytes[1..], &bytes[..]);
+        prop_assert_eq!(VersionedMessage::parse(&versioned_bytes).unwrap(), versioned);
+
+        let v0 = message.to_v0().unwrap();
+        v0.sanitize().unwrap();
+        prop_assert_eq!(v1::Message::from_v0(&v0), message.clone());
+        prop_assert_eq!(VersionedMessage::V0(v0.clone()).compute_budget_request(), message.compute_budget_request());
+    }
+
+    #[test]
+    fn test_roundtrip_v2(message in v2_message()) {
+        message.sanitize().unwrap();
+
+        let bytes = bincode::serialize(&message).unwrap();
+        prop_assert_eq!(bincode::deserialize::<v2::Message>(&bytes).unwrap(), message.clone());
+
+        // everything from the counters on is laid out exactly like v0
+        let counters = MessageLayout::of(MessageVersion::V2)
+            .fixed_offset("num_required_signatures")
+            .unwrap()
+            - 1;
+        let v0 = solana_message::v0::Message {
+            header: message.header.as_message_header(),
+            account_keys: message.account_keys.clone(),
+            recent_blockhash: message.recent_blockhash,
+            instructions: message.instructions.clone(),
+            address_table_lookups: message.address_table_lookups.clone(),
+        };
+        prop_assert_eq!(&bytes[counters..], &bincode::serialize(&v0).unwrap()[..]);
+
+        let versioned = VersionedMessage::V2(message.clone());
+        let versioned_bytes = versioned.serialize();
+        prop_assert_eq!(&versioned_bytes[1..], &bytes[..]);
+        prop_assert_eq!(VersionedMessage::parse(&versioned_bytes).unwrap(), versioned);
+
+        let v0 = message.to_v0().unwrap();
+        v0.sanitize().unwrap();
+        prop_assert_eq!(v2::Message::from_v0(&v0), message.clone());
+        prop_assert_eq!(VersionedMessage::V0(v0.clone()).compute_budget_request(), message.compute_budget_request());
+    }
+
+    #[test]
+    fn test_roundtrip_v3(message in v3_message()) {
+        message.sanitize().unwrap();
+
+        let bytes = bincode::serialize(&message).unwrap();
+        prop_assert_eq!(bincode::deserialize::<v3::Message>(&bytes).unwrap(), message.clone());
+
+        // everything after the compute budget header is laid out exactly like v0
+        let header_size = message.compute_budget_header.serialized_size();
+        let v0 = solana_message::v0::Message {
+            header: message.header,
+            account_keys: message.account_keys.clone(),
+            recent_blockhash: message.recent_blockhash,
+            instructions: message.instructions.clone(),
+            address_table_lookups: message.address_table_lookups.clone(),
+        };
+        prop_assert_eq!(&bytes[header_size..], &bincode::serialize(&v0).unwrap()[..]);
+
+        let versioned = VersionedMessage::V3(message.clone());
+        let versioned_bytes = versioned.serialize();
+        prop_assert_eq!(&versioned_bytes[1..], &bytes[..]);
+        prop_assert_eq!(VersionedMessage::parse(&versioned_bytes).unwrap(), versioned);
+
+        if message.compute_budget_header.max_fee_lamports().is_some() {
+            // there's no instruction to carry a fee cap back to v0
+            prop_assert_eq!(message.to_v0(), Err(ConvertError::MaxFeeUnsupported));
+        } else {
+            let v0 = message.to_v0().unwrap();
+            v0.sanitize().unwrap();
+            prop_assert_eq!(v3::Message::from_v0(&v0), message.clone());
+            prop_assert_eq!(VersionedMessage::V0(v0.clone()).compute_budget_request(), message.compute_budget_request());
+        }
+    }
+
+    #[cfg(feature = "v3b")]
+    #[test]
+    fn test_roundtrip_v3b(message in v3b_message()) {
+        message.sanitize().unwrap();
+
+        let bytes = bincode::serialize(&message).unwrap();
+        prop_assert_eq!(bincode::deserialize::<v3b::Message>(&bytes).unwrap(), message.clone());
+
+        // everything before the compute budget header is laid out exactly like v0
+        let header_size = message.compute_budget_header.serialized_size();
+        let v0 = solana_message::v0::Message {
+            header: message.header,
+            account_keys: message.account_keys.clone(),
+            recent_blockhash: message.recent_blockhash,
+            instructions: message.instructions.clone(),
+            address_table_lookups: message.address_table_lookups.clone(),
+        };
+        prop_assert_eq!(&bytes[..bytes.len() - header_size], &bincode::serialize(&v0).unwrap()[..]);
+
+        let versioned = VersionedMessage::V3b(message.clone());
+        let versioned_bytes = versioned.serialize();
+        prop_assert_eq!(&versioned_bytes[1..], &bytes[..]);
+        prop_assert_eq!(VersionedMessage::parse(&versioned_bytes).unwrap(), versioned);
+
+        if message.compute_budget_header.max_fee_lamports().is_some() {
+            // there's no instruction to carry a fee cap back to v0
+            prop_assert_eq!(message.to_v0(), Err(ConvertError::MaxFeeUnsupported));
+        } else {
+            let v0 = message.to_v0().unwrap();
+            v0.sanitize().unwrap();
+            prop_assert_eq!(v3b::Message::from_v0(&v0), message.clone());
+            prop_assert_eq!(VersionedMessage::V0(v0.clone()).compute_budget_request(), message.compute_budget_request());
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_v4(message in v4_message()) {
+        message.sanitize().unwrap();
+
+        let bytes = bincode::serialize(&message).unwrap();
+        prop_assert_eq!(bincode::deserialize::<v4::Message>(&bytes).unwrap(), message.clone());
+
+        // everything after the compute budget header is laid out exactly like v0
+        let header_size = message.compute_budget_header.serialized_size();
+        let v0 = solana_message::v0::Message {
+            header: message.header,
+            account_keys: message.account_keys.clone(),
+            recent_blockhash: message.recent_blockhash,
+            instructions: message.instructions.clone(),
+            address_table_lookups: message.address_table_lookups.clone(),
+        };
+        prop_assert_eq!(&bytes[header_size..], &bincode::serialize(&v0).unwrap()[..]);
+
+        let versioned = VersionedMessage::V4(message.clone());
+        let versioned_bytes = versioned.serialize();
+        prop_assert_eq!(&versioned_bytes[1..], &bytes[..]);
+        prop_assert_eq!(VersionedMessage::parse(&versioned_bytes).unwrap(), versioned);
+
+        if message.compute_budget_header.max_fee_lamports().is_some() {
+            // there's no instruction to carry a fee cap back to v0
+            prop_assert_eq!(message.to_v0(), Err(ConvertError::MaxFeeUnsupported));
+        } else {
+            let v0 = message.to_v0().unwrap();
+            v0.sanitize().unwrap();
+            prop_assert_eq!(v4::Message::from_v0(&v0), message.clone());
+            prop_assert_eq!(VersionedMessage::V0(v0.clone()).compute_budget_request(), message.compute_budget_request());
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_v5(message in v5_message()) {
+        message.sanitize().unwrap();
+
+        let bytes = bincode::serialize(&message).unwrap();
+        prop_assert_eq!(bincode::deserialize::<v5::Message>(&bytes).unwrap(), message.clone());
+
+        // everything after the compute budget header is laid out exactly like v0
+        let header_size = message.compute_budget_header.serialized_size();
+        let v0 = solana_message::v0::Message {
+            header: message.header,
+            account_keys: message.account_keys.clone(),
+            recent_blockhash: message.recent_blockhash,
+            instructions: message.instructions.clone(),
+            address_table_lookups: message.address_table_lookups.clone(),
+        };
+        prop_assert_eq!(&bytes[header_size..], &bincode::serialize(&v0).unwrap()[..]);
+
+        let versioned = VersionedMessage::V5(message.clone());
+        let versioned_bytes = versioned.serialize();
+        prop_assert_eq!(&versioned_bytes[1..], &bytes[..]);
+        prop_assert_eq!(VersionedMessage::parse(&versioned_bytes).unwrap(), versioned);
+
+        if message.compute_budget_header.max_fee_lamports().is_some() {
+            // there's no instruction to carry a fee cap back to v0
+            prop_assert_eq!(message.to_v0(), Err(ConvertError::MaxFeeUnsupported));
+        } else {
+            let v0 = message.to_v0().unwrap();
+            v0.sanitize().unwrap();
+            prop_assert_eq!(v5::Message::from_v0(&v0), message.clone());
+            prop_assert_eq!(VersionedMessage::V0(v0.clone()).compute_budget_request(), message.compute_budget_request());
+        }
+    }
+
+    #[test]
+    fn test_convert_between_versions(message in v3_message()) {
+        let has_max_fee = message.compute_budget_header.max_fee_lamports().is_some();
+        let versioned = VersionedMessage::V3(message);
+        let request = versioned.compute_budget_request();
+        for &version in MessageVersion::ALL {
+            let converted = match versioned.convert(version) {
+                Ok(converted) => converted,
+                // a fee cap can't go through v0
+                Err(ConvertError::MaxFeeUnsupported) => {
+                    prop_assert!(has_max_fee);
+                    continue;
+                }
+                // otherwise only legacy can refuse, when there are lookups
+                Err(_) => {
+                    prop_assert!(versioned.address_table_lookups().is_some_and(|l| !l.is_empty()));
+                    continue;
+                }
+            };
+            converted.sanitize().unwrap();
+            prop_assert_eq!(converted.compute_budget_request(), request.clone());
+        }
+    }
+
+    #[test]
+    fn test_fold_compute_budget(message in v0_message()) {
+        message.sanitize().unwrap();
+
+        // folding the instructions into a header, and unfolding them again,
+        // never changes the budget
+        let versioned = VersionedMessage::V0(message);
+        let request = versioned.compute_budget_request().unwrap();
+        for &version in MessageVersion::ALL {
+            let Ok(converted) = versioned.convert(version) else {
+                prop_assert!(versioned.address_table_lookups().is_some_and(|l| !l.is_empty()));
+                continue;
+            };
+            converted.sanitize().unwrap();
+            prop_assert_eq!(converted.compute_budget_request(), Ok(request));
+            if matches!(version, MessageVersion::V3 | MessageVersion::V4) {
+                // these headers can carry every value an instruction sets
+                prop_assert!(compute_budget_instruction_indexes(
+                    converted.static_account_keys(),
+                    converted.instructions()
+                )
+                .is_empty());
+            }
+            let unfolded = VersionedMessage::V0(converted.to_v0().unwrap());
+            unfolded.sanitize().unwrap();
+            prop_assert_eq!(unfolded.compute_budget_request(), Ok(request));
+            prop_assert_eq!(unfolded.convert(version).unwrap(), converted);
+        }
+    }
+}
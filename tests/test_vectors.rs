@@ -0,0 +1,388 @@
+//! Golden encodings of every candidate version, for other implementations to
+//! run conformance against.
+//!
+//! The vectors live in `test-vectors/manifest.json`. Run with `V1TX_BLESS=1` to
+//! regenerate the file after an intentional format change, with the `v3b`
+//! feature on since the file covers every version.
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use serde_json::{Value, json};
+use solana_hash::Hash;
+use solana_message::{
+    MessageHeader, compiled_instruction::CompiledInstruction, v0::MessageAddressTableLookup,
+};
+use solana_pubkey::Pubkey;
+#[cfg(feature = "v3b")]
+use v1tx::v3b;
+use v1tx::{v1, v2, v3, v4, v5, versioned::VersionedMessage};
+
+const MANIFEST_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test-vectors/manifest.json");
+
+const COMPUTE_UNIT_LIMIT: u32 = 200_000;
+const COMPUTE_UNIT_PRICE: u64 = 1_000_000;
+const LOADED_ACCOUNTS_DATA_LIMIT: u32 = 65_536;
+const REQUESTED_HEAP_BYTES: u32 = 262_144;
+const MAX_FEE_LAMPORTS: u64 = 250_000;
+
+/// The message body shared by every version.
+struct Body {
+    name: &'static str,
+    header: MessageHeader,
+    account_keys: Vec<Pubkey>,
+    recent_blockhash: Hash,
+    instructions: Vec<CompiledInstruction>,
+    address_table_lookups: Vec<MessageAddressTableLookup>,
+}
+
+fn bodies() -> Vec<Body> {
+    let payer = Pubkey::new_from_array([1; 32]);
+    let recent_blockhash = Hash::new_from_array([0xab; 32]);
+    vec![
+        Body {
+            name: "noop",
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 0,
+            },
+            account_keys: vec![payer],
+            recent_blockhash,
+            instructions: vec![],
+            address_table_lookups: vec![],
+        },
+        Body {
+            name: "transfer_with_lookup",
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 1,
+            },
+            account_keys: vec![payer, Pubkey::new_from_array([0; 32])],
+            recent_blockhash,
+            // system program transfer of 1_000 lamports to the first looked-up
+            // writable account
+            instructions: vec![CompiledInstruction::new_from_raw_parts(
+                1,
+                vec![2, 0, 0, 0, 0xe8, 0x03, 0, 0, 0, 0, 0, 0],
+                vec![0, 2],
+            )],
+            address_table_lookups: vec![MessageAddressTableLookup {
+                account_key: Pubkey::new_from_array([2; 32]),
+                writable_indexes: vec![0],
+                readonly_indexes: vec![1, 2],
+            }],
+        },
+    ]
+}
+
+/// Limit, price, loaded accounts data limit, heap and max fee, each present or
+/// absent.
+type Budget = (
+    Option<u32>,
+    Option<u64>,
+    Option<u32>,
+    Option<u32>,
+    Option<u64>,
+);
+
+/// Every combination of present/absent compute budget fields, in
+/// `ComputeBudgetFlags` bit order.
+fn budget_combinations() -> impl Iterator<Item = Budget> {
+    (0u8..32).map(|bits| {
+        (
+            (bits & 0b00001 != 0).then_some(COMPUTE_UNIT_LIMIT),
+            (bits & 0b00010 != 0).then_some(COMPUTE_UNIT_PRICE),
+            (bits & 0b00100 != 0).then_some(LOADED_ACCOUNTS_DATA_LIMIT),
+            (bits & 0b01000 != 0).then_some(REQUESTED_HEAP_BYTES),
+            (bits & 0b10000 != 0).then_some(MAX_FEE_LAMPORTS),
+        )
+    })
+}
+
+fn vectors() -> Vec<(String, VersionedMessage)> {
+    let mut vectors = vec![];
+    for body in bodies() {
+        for (limit, price, loaded, heap, max_fee) in budget_combinations() {
+            let suffix = format!(
+                "limit_{}_price_{}_loaded_{}_heap_{}_max_fee_{}",
+                limit.is_some() as u8,
+                price.is_some() as u8,
+                loaded.is_some() as u8,
+                heap.is_some() as u8,
+                max_fee.is_some() as u8,
+            );
+
+            // v1 only has the limit and price fields
+            if loaded.is_none() && heap.is_none() && max_fee.is_none() {
+                vectors.push((
+                    format!("v1_{}_{suffix}", body.name),
+                    VersionedMessage::V1(v1::Message {
+                        header: v1::MessageHeader {
+                            compute_unit_price: price.unwrap_or_default(),
+                            compute_unit_limit: limit.unwrap_or_default(),
+                            num_required_signatures: body.header.num_required_signatures,
+                            num_readonly_signed_accounts: body.header.num_readonly_signed_accounts,
+                            num_readonly_unsigned_accounts: body
+                                .header
+                                .num_readonly_unsigned_accounts,
+                        },
+                        account_keys: body.account_keys.clone(),
+                        recent_blockhash: body.recent_blockhash,
+                        instructions: body.instructions.clone(),
+                        address_table_lookups: body.address_table_lookups.clone(),
+                    }),
+                ));
+            }
+
+            // and neither v1 nor v2 can cap the fee
+            if max_fee.is_none() {
+                vectors.push((
+                    format!("v2_{}_{suffix}", body.name),
+                    VersionedMessage::V2(v2::Message {
+                        header: v2::MessageHeader {
+                            compute_unit_price: price.unwrap_or_default(),
+                            compute_unit_limit: limit.unwrap_or_default(),
+                            loaded_accounts_data_limit: loaded.unwrap_or_default(),
+                            requested_heap_bytes: heap.unwrap_or_default(),
+                            num_required_signatures: body.header.num_required_signatures,
+                            num_readonly_signed_accounts: body.header.num_readonly_signed_accounts,
+                            num_readonly_unsigned_accounts: body
+                                .header
+                                .num_readonly_unsigned_accounts,
+                        },
+                        account_keys: body.account_keys.clone(),
+                        recent_blockhash: body.recent_blockhash,
+                        instructions: body.instructions.clone(),
+                        address_table_lookups: body.address_table_lookups.clone(),
+                    }),
+                ));
+            }
+
+            vectors.push((
+                format!("v3_{}_{suffix}", body.name),
+                VersionedMessage::V3(v3::Message {
+                    compute_budget_header: v3::ComputeBudgetHeader::new(limit, price, loaded, heap)
+                        .with_max_fee_lamports(max_fee),
+                    header: body.header,
+                    account_keys: body.account_keys.clone(),
+                    recent_blockhash: body.recent_blockhash,
+                    instructions: body.instructions.clone(),
+                    address_table_lookups: body.address_table_lookups.clone(),
+                }),
+            ));
+
+            #[cfg(feature = "v3b")]
+            vectors.push((
+                format!("v3b_{}_{suffix}", body.name),
+                VersionedMessage::V3b(v3b::Message {
+                    header: body.header,
+                    account_keys: body.account_keys.clone(),
+                    recent_blockhash: body.recent_blockhash,
+                    instructions: body.instructions.clone(),
+                    address_table_lookups: body.address_table_lookups.clone(),
+                    compute_budget_header: v3::ComputeBudgetHeader::new(limit, price, loaded, heap)
+                        .with_max_fee_lamports(max_fee),
+                }),
+            ));
+
+            vectors.push((
+                format!("v4_{}_{suffix}", body.name),
+                VersionedMessage::V4(v4::Message {
+                    compute_budget_header: v4::ComputeBudgetHeader::new(limit, price, loaded, heap)
+                        .with_max_fee_lamports(max_fee),
+                    header: body.header,
+                    account_keys: body.account_keys.clone(),
+                    recent_blockhash: body.recent_blockhash,
+                    instructions: body.instructions.clone(),
+                    address_table_lookups: body.address_table_lookups.clone(),
+                }),
+            ));
+
+            let scaled_price = price.map(|price| v5::ScaledPrice::try_from(price).unwrap());
+            vectors.push((
+                format!("v5_{}_{suffix}", body.name),
+                VersionedMessage::V5(v5::Message {
+                    compute_budget_header: v5::ComputeBudgetHeader::new(
+                        limit,
+                        scaled_price,
+                        loaded,
+                        heap,
+                    )
+                    .with_max_fee_lamports(max_fee),
+                    header: body.header,
+                    account_keys: body.account_keys.clone(),
+                    recent_blockhash: body.recent_blockhash,
+                    instructions: body.instructions.clone(),
+                    address_table_lookups: body.address_table_lookups.clone(),
+                }),
+            ));
+        }
+    }
+    vectors.sort_by(|(a, _), (b, _)| a.cmp(b));
+    vectors
+}
+
+/// Describe the decoded fields of `message` so other implementations can
+/// check their decoders, not just their byte output.
+fn describe(message: &VersionedMessage) -> Value {
+    let compute_budget = match message {
+        VersionedMessage::V1(message) => json!({
+            "computeUnitLimit": message.header.compute_unit_limit,
+            "computeUnitPrice": message.header.compute_unit_price.to_string(),
+        }),
+        VersionedMessage::V2(message) => json!({
+            "computeUnitLimit": message.header.compute_unit_limit,
+            "computeUnitPrice": message.header.compute_unit_price.to_string(),
+            "loadedAccountsDataLimit": message.header.loaded_accounts_data_limit,
+            "requestedHeapBytes": message.header.requested_heap_bytes,
+        }),
+        VersionedMessage::V3(message) => {
+            let header = &message.compute_budget_header;
+            json!({
+                "flags": header.flags().bits(),
+                "computeUnitLimit": header.compute_unit_limit(),
+                "computeUnitPrice": header.compute_unit_price().map(|price| price.to_string()),
+                "loadedAccountsDataLimit": header.loaded_accounts_data_limit(),
+                "requestedHeapBytesLimit": header.requested_heap_bytes_limit(),
+                "maxFeeLamports": header.max_fee_lamports().map(|fee| fee.to_string()),
+            })
+        }
+        #[cfg(feature = "v3b")]
+        VersionedMessage::V3b(message) => {
+            let header = &message.compute_budget_header;
+            json!({
+                "flags": header.flags().bits(),
+                "computeUnitLimit": header.compute_unit_limit(),
+                "computeUnitPrice": header.compute_unit_price().map(|price| price.to_string()),
+                "loadedAccountsDataLimit": header.loaded_accounts_data_limit(),
+                "requestedHeapBytesLimit": header.requested_heap_bytes_limit(),
+                "maxFeeLamports": header.max_fee_lamports().map(|fee| fee.to_string()),
+            })
+        }
+        VersionedMessage::V4(message) => {
+            let header = &message.compute_budget_header;
+            json!({
+                "flags": header.flags().bits(),
+                "computeUnitLimit": header.compute_unit_limit(),
+                "computeUnitPrice": header.compute_unit_price().map(|price| price.to_string()),
+                "loadedAccountsDataLimit": header.loaded_accounts_data_limit(),
+                "requestedHeapBytesLimit": header.requested_heap_bytes_limit(),
+                "maxFeeLamports": header.max_fee_lamports().map(|fee| fee.to_string()),
+            })
+        }
+        VersionedMessage::V5(message) => {
+            let header = &message.compute_budget_header;
+            json!({
+                "flags": header.flags().bits(),
+                "computeUnitLimit": header.compute_unit_limit(),
+                "computeUnitPrice": header.scaled_compute_unit_price().map(|price| json!({
+                    "exponent": price.exponent(),
+                    "mantissa": price.mantissa(),
+                    "value": price.value().to_string(),
+                })),
+                "loadedAccountsDataLimit": header.loaded_accounts_data_limit(),
+                "requestedHeapBytesLimit": header.requested_heap_bytes_limit(),
+                "maxFeeLamports": header.max_fee_lamports().map(|fee| fee.to_string()),
+            })
+        }
+        _ => unreachable!("only candidate versions have vectors"),
+    };
+    let header = message.header();
+
+    json!({
+        "version": message.version().number(),
+        "computeBudget": compute_budget,
+        "header": {
+            "numRequiredSignatures": header.num_required_signatures,
+            "numReadonlySignedAccounts": header.num_readonly_signed_accounts,
+            "numReadonlyUnsignedAccounts": header.num_readonly_unsigned_accounts,
+        },
+        "accountKeys": message
+            .static_account_keys()
+            .iter()
+            .map(Pubkey::to_string)
+            .collect::<Vec<_>>(),
+        "recentBlockhash": message.recent_blockhash().to_string(),
+        "instructions": message
+            .instructions()
+            .iter()
+            .map(|ix| json!({
+                "programIdIndex": ix.program_id_index,
+                "accounts": ix.accounts,
+                "data": hex::encode(&ix.data),
+            }))
+            .collect::<Vec<_>>(),
+        "addressTableLookups": message
+            .address_table_lookups()
+            .unwrap_or_default()
+            .iter()
+            .map(|lookup| json!({
+                "accountKey": lookup.account_key.to_string(),
+                "writableIndexes": lookup.writable_indexes,
+                "readonlyIndexes": lookup.readonly_indexes,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn manifest() -> Value {
+    let vectors: Vec<Value> = vectors()
+        .into_iter()
+        .map(|(name, message)| {
+            let bytes = message.serialize();
+            json!({
+                "name": name,
+                "hex": hex::encode(&bytes),
+                "base64": BASE64_STANDARD.encode(&bytes),
+                "message": describe(&message),
+            })
+        })
+        .collect();
+    json!({
+        "description": "Serialized messages for the candidate compute budget message versions. \
+            `hex` and `base64` hold the same bytes, including the version prefix. \
+            `computeUnitPrice` is a decimal string since it may not fit in a JSON number.",
+        "vectors": vectors,
+    })
+}
+
+#[test]
+fn test_vectors() {
+    let expected = manifest();
+    if std::env::var_os("V1TX_BLESS").is_some() {
+        if !cfg!(feature = "v3b") {
+            panic!("bless with the v3b feature on, the file covers every version");
+        }
+        let json = serde_json::to_string_pretty(&expected).unwrap() + "\n";
+        std::fs::write(MANIFEST_PATH, json).unwrap();
+    }
+
+    let checked_in = std::fs::read_to_string(MANIFEST_PATH).unwrap();
+    #[allow(unused_mut)]
+    let mut manifest: Value = serde_json::from_str(&checked_in).unwrap();
+    // without the feature v3b's vectors can't be generated or decoded
+    #[cfg(not(feature = "v3b"))]
+    manifest["vectors"]
+        .as_array_mut()
+        .unwrap()
+        .retain(|vector| !vector["name"].as_str().unwrap().starts_with("v3b_"));
+    assert!(
+        manifest == expected,
+        "{MANIFEST_PATH} is out of date, rerun with V1TX_BLESS=1 if the format change is intended"
+    );
+
+    // decode the checked-in bytes independently of the generator
+    for vector in manifest["vectors"].as_array().unwrap() {
+        let bytes = hex::decode(vector["hex"].as_str().unwrap()).unwrap();
+        assert_eq!(
+            BASE64_STANDARD
+                .decode(vector["base64"].as_str().unwrap())
+                .unwrap(),
+            bytes
+        );
+
+        let message = VersionedMessage::parse(&bytes).unwrap();
+        assert_eq!(message.serialize(), bytes);
+        assert_eq!(describe(&message), vector["message"], "{}", vector["name"]);
+    }
+}